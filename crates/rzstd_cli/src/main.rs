@@ -1,12 +1,11 @@
 use std::{
     fs::File,
-    io::{BufReader, BufWriter, stdout},
+    io::{BufReader, BufWriter, Write, stdout},
     path::PathBuf,
 };
 
 use clap::{Args, Parser, Subcommand};
 use miette::IntoDiagnostic;
-use rzstd_decompress::MAX_BLOCK_SIZE;
 use tracing_subscriber::{EnvFilter, prelude::*};
 
 #[derive(Parser)]
@@ -29,6 +28,11 @@ struct DecompressArgs {
 
     /// Output file
     output: Option<PathBuf>,
+
+    /// Memory-map the input instead of reading it through a buffer. Inputs
+    /// that aren't regular files are still read through a buffer.
+    #[arg(long)]
+    mmap: bool,
 }
 
 fn main() -> miette::Result<()> {
@@ -65,18 +69,29 @@ fn main() -> miette::Result<()> {
             };
 
             let input_file = File::open(args.input).into_diagnostic()?;
-            let reader = BufReader::new(input_file);
-
             let mut writer = BufWriter::new(output_file);
 
-            let window_size = 100 * 1024 * 1024;
-            let mut window_buffer = vec![0u8; window_size + MAX_BLOCK_SIZE as usize];
-
-            let mut decoder =
-                rzstd_decompress::Decoder::new(reader, &mut window_buffer, window_size);
-
-            decoder.decode(&mut writer).into_diagnostic()?;
+            if args.mmap && input_file.metadata().into_diagnostic()?.is_file() {
+                // SAFETY: the map is only read, and the input isn't expected
+                // to be modified while it's being decompressed.
+                let input =
+                    unsafe { memmap2::Mmap::map(&input_file) }.into_diagnostic()?;
+                decompress(&input[..], &mut writer)?;
+            } else {
+                decompress(BufReader::new(input_file), &mut writer)?;
+            }
         }
     }
     Ok(())
 }
+
+fn decompress(
+    src: impl std::io::Read + std::fmt::Debug,
+    writer: &mut impl Write,
+) -> miette::Result<()> {
+    // Sized from each frame's header, up to the largest window the format
+    // allows.
+    let mut decoder = rzstd_decompress::Decoder::new_auto(src);
+    decoder.decode(&mut *writer).into_diagnostic()?;
+    writer.flush().into_diagnostic()
+}
@@ -0,0 +1,35 @@
+use std::{path::Path, process::Command};
+
+fn decompress(dir: &Path, input: &Path, output: &str, extra: &[&str]) -> Vec<u8> {
+    let status = Command::new(env!("CARGO_BIN_EXE_rzstd"))
+        .current_dir(dir)
+        .arg("decompress")
+        .arg(input)
+        .arg(output)
+        .args(extra)
+        .status()
+        .expect("rzstd should run");
+    assert!(status.success(), "rzstd {extra:?} failed");
+
+    std::fs::read(dir.join(output)).expect("output should be written")
+}
+
+#[test]
+fn mmap_matches_buffered() {
+    let dir = std::env::temp_dir().join(format!("rzstd-cli-mmap-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("temp dir should be writable");
+
+    let content = (0..1_000_000u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 28) as u8)
+        .collect::<Vec<_>>();
+    let input = dir.join("input.zst");
+    let compressed = zstd::encode_all(&content[..], 3).expect("valid input");
+    std::fs::write(&input, compressed).expect("temp dir should be writable");
+
+    let buffered = decompress(&dir, &input, "buffered", &[]);
+    let mapped = decompress(&dir, &input, "mapped", &["--mmap"]);
+
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(buffered == content, "buffered output differs");
+    assert!(mapped == buffered, "mapped output differs");
+}
@@ -0,0 +1,265 @@
+use rzstd_decompress::{MAGIC_NUM, MAX_BLOCK_SIZE, frame};
+use xxhash_rust::xxh64::xxh64;
+
+use crate::errors::Error;
+
+const BLOCK_TYPE_RAW: u32 = 0;
+const BLOCK_TYPE_RLE: u32 = 1;
+
+/// Shortest run of a single byte worth splitting into its own RLE block. Below
+/// this, the extra block headers around the run cost more than they save.
+const RLE_MIN_RUN: usize = 16;
+
+/// Writes zstd frames to `W`.
+///
+/// Each call to [Encoder::encode] produces one complete single-segment frame,
+/// carrying the content size in its header. Runs of a single repeated byte
+/// are stored as RLE blocks and everything else as raw blocks; there is no
+/// entropy coding yet.
+#[derive(Debug)]
+pub struct Encoder<W: std::io::Write> {
+    dst: W,
+    checksum: bool,
+}
+
+impl<W: std::io::Write> Encoder<W> {
+    pub fn new(dst: W) -> Self {
+        Self {
+            dst,
+            checksum: false,
+        }
+    }
+
+    /// Whether to append the 32-bit XXH64 content checksum to each frame.
+    pub fn with_checksum(mut self, enabled: bool) -> Self {
+        self.checksum = enabled;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.dst
+    }
+
+    /// Encodes `src` as a single frame.
+    pub fn encode(&mut self, src: &[u8]) -> Result<(), Error> {
+        self.dst.write_all(&MAGIC_NUM.to_le_bytes())?;
+        self.write_frame_header(src.len() as u64)?;
+
+        let mut blocks = Blocks { src }.peekable();
+        if blocks.peek().is_none() {
+            // A frame holds at least one block, even for empty content.
+            self.write_block_header(true, BLOCK_TYPE_RAW, 0)?;
+        }
+
+        while let Some(block) = blocks.next() {
+            let last_block = blocks.peek().is_none();
+            match block {
+                Block::Raw(data) => {
+                    self.write_block_header(
+                        last_block,
+                        BLOCK_TYPE_RAW,
+                        data.len() as u32,
+                    )?;
+                    self.dst.write_all(data)?;
+                }
+                Block::RLE { byte, len } => {
+                    self.write_block_header(last_block, BLOCK_TYPE_RLE, len as u32)?;
+                    self.dst.write_all(&[byte])?;
+                }
+            }
+        }
+
+        if self.checksum {
+            let checksum = xxh64(src, 0) as u32;
+            self.dst.write_all(&checksum.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a single-segment frame header, picking the smallest
+    /// Frame_Content_Size field able to hold `content_size`.
+    fn write_frame_header(&mut self, content_size: u64) -> Result<(), Error> {
+        frame::Header::builder()
+            .content_size(content_size)
+            .checksum(self.checksum)
+            .build()?
+            .write(&mut self.dst)?;
+        Ok(())
+    }
+
+    fn write_block_header(
+        &mut self,
+        last_block: bool,
+        block_type: u32,
+        block_size: u32,
+    ) -> Result<(), Error> {
+        let raw = (last_block as u32) | (block_type << 1) | (block_size << 3);
+        self.dst.write_all(&raw.to_le_bytes()[..3])?;
+        Ok(())
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+enum Block<'a> {
+    Raw(&'a [u8]),
+    RLE { byte: u8, len: usize },
+}
+
+/// Greedily splits the input into blocks of at most [MAX_BLOCK_SIZE] bytes:
+/// runs of at least [RLE_MIN_RUN] equal bytes become RLE blocks, and the
+/// stretches between them raw blocks.
+struct Blocks<'a> {
+    src: &'a [u8],
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Block<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = *self.src.first()?;
+        let src = &self.src[..self.src.len().min(MAX_BLOCK_SIZE as usize)];
+
+        let run = src.iter().take_while(|&&b| b == first).count();
+        if run >= RLE_MIN_RUN {
+            self.src = &self.src[run..];
+            return Some(Block::RLE {
+                byte: first,
+                len: run,
+            });
+        }
+
+        // Extend the raw block up to the start of the next long enough run.
+        let mut len = src.len();
+        let mut run_start = 0;
+        for i in 1..src.len() {
+            if src[i] != src[run_start] {
+                run_start = i;
+            } else if i + 1 - run_start == RLE_MIN_RUN {
+                len = run_start;
+                break;
+            }
+        }
+
+        let (raw, rest) = self.src.split_at(len);
+        self.src = rest;
+        Some(Block::Raw(raw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rzstd_decompress::Decoder;
+
+    use super::*;
+
+    fn encode(src: &[u8], checksum: bool) -> Vec<u8> {
+        let mut encoder = Encoder::new(Vec::new()).with_checksum(checksum);
+        encoder.encode(src).expect("writing to a Vec cannot fail");
+        encoder.into_inner()
+    }
+
+    fn decode(src: &[u8], content_size: usize) -> Vec<u8> {
+        let mut window_buf = vec![0; content_size + MAX_BLOCK_SIZE as usize];
+        let mut decoder = Decoder::new(src, &mut window_buf, content_size);
+
+        let mut out = Vec::new();
+        decoder
+            .decode(&mut out)
+            .expect("encoded frame should decode");
+        out
+    }
+
+    #[test]
+    fn test_encode_empty() {
+        let encoded = encode(&[], false);
+
+        // Magic, descriptor, 1-byte FCS and one empty last raw block.
+        assert_eq!(encoded.len(), 4 + 1 + 1 + 3);
+        assert_eq!(decode(&encoded, 0), b"");
+        assert_eq!(zstd::decode_all(&encoded[..]).expect("valid frame"), b"");
+    }
+
+    #[test]
+    fn test_encode_fcs_boundaries() {
+        for len in [0xFF, 0x100, 0x100FF, 0x1_0100] {
+            let src = vec![b'a'; len];
+            let encoded = encode(&src, false);
+
+            assert_eq!(decode(&encoded, len), src, "len={len}");
+        }
+    }
+
+    #[test]
+    fn test_encode_multiple_blocks() {
+        let src = (0..3 * MAX_BLOCK_SIZE as usize + 7)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let encoded = encode(&src, true);
+
+        assert_eq!(decode(&encoded, src.len()), src);
+        assert_eq!(zstd::decode_all(&encoded[..]).expect("valid frame"), src);
+    }
+
+    #[test]
+    fn test_encode_constant_run() {
+        let src = vec![0x2a; 3 * MAX_BLOCK_SIZE as usize + 100];
+        let encoded = encode(&src, true);
+
+        // Four RLE blocks of a single byte each, plus the frame overhead.
+        assert!(encoded.len() < 32, "encoded.len()={}", encoded.len());
+        assert_eq!(decode(&encoded, src.len()), src);
+        assert_eq!(zstd::decode_all(&encoded[..]).expect("valid frame"), src);
+    }
+
+    #[test]
+    fn test_encode_mixed_runs() {
+        let src = [
+            &b"leading raw bytes"[..],
+            &[0; 1000],
+            b"short run: aaaa",
+            &[0xff; RLE_MIN_RUN],
+            b"trailing raw bytes",
+        ]
+        .concat();
+
+        let blocks = Blocks { src: &src }
+            .map(|b| match b {
+                Block::Raw(data) => (BLOCK_TYPE_RAW, data.len()),
+                Block::RLE { len, .. } => (BLOCK_TYPE_RLE, len),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            blocks,
+            [
+                (BLOCK_TYPE_RAW, 17),
+                (BLOCK_TYPE_RLE, 1000),
+                (BLOCK_TYPE_RAW, 15),
+                (BLOCK_TYPE_RLE, RLE_MIN_RUN),
+                (BLOCK_TYPE_RAW, 18),
+            ]
+        );
+
+        let encoded = encode(&src, true);
+        assert_eq!(decode(&encoded, src.len()), src);
+        assert_eq!(zstd::decode_all(&encoded[..]).expect("valid frame"), src);
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(
+            runs in proptest::collection::vec((any::<u8>(), 1..64usize), 0..128),
+            checksum in any::<bool>(),
+        ) {
+            let src = runs
+                .into_iter()
+                .flat_map(|(byte, len)| std::iter::repeat_n(byte, len))
+                .collect::<Vec<_>>();
+            let encoded = encode(&src, checksum);
+
+            prop_assert_eq!(decode(&encoded, src.len()), src.clone());
+            prop_assert_eq!(zstd::decode_all(&encoded[..]).expect("valid frame"), src);
+        }
+    }
+}
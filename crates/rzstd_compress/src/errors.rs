@@ -0,0 +1,10 @@
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum Error {
+    #[error(transparent)]
+    #[diagnostic(code(rzstd::compress::io))]
+    IO(#[from] std::io::Error),
+
+    #[error(transparent)]
+    #[diagnostic(code(rzstd::compress::frame_header))]
+    FrameHeader(#[from] rzstd_decompress::Error),
+}
@@ -0,0 +1,5 @@
+mod encoder;
+mod errors;
+
+pub use encoder::Encoder;
+pub use errors::Error;
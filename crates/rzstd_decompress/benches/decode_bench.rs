@@ -86,5 +86,201 @@ fn bench_silesia_corpus(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_silesia_corpus);
+/// Skewed but match-free bytes, so that blocks are almost entirely
+/// Huffman-coded literals split over 4 streams.
+fn literal_heavy_input(len: usize) -> Vec<u8> {
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            // Sum of two uniform draws: a triangular, Huffman-friendly spread.
+            ((state & 0x7F) + ((state >> 8) & 0x7F)) as u8
+        })
+        .collect()
+}
+
+fn bench_literals(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Literals");
+
+    let expected = literal_heavy_input(16 * 1024 * 1024);
+    let compressed = zstd::encode_all(&expected[..], 1).unwrap();
+
+    group.throughput(Throughput::Bytes(expected.len() as u64));
+    group.bench_function("rzstd", |b| {
+        b.iter(|| {
+            let window_size = 16 * 1024 * 1024 + MAX_BLOCK_SIZE as usize;
+            let mut window_buffer = vec![0u8; window_size];
+            let mut output_buffer = Vec::with_capacity(expected.len());
+            let mut decoder = rzstd_decompress::Decoder::new(
+                black_box(&compressed[..]),
+                &mut window_buffer,
+                window_size,
+            );
+            decoder.decode(&mut output_buffer).unwrap();
+            assert_eq!(output_buffer, expected);
+        })
+    });
+    // Unlike the above, neither stores nor checks the output: this is the
+    // decoding alone.
+    group.bench_function("rzstd (sink)", |b| {
+        b.iter(|| {
+            let window_size = 16 * 1024 * 1024 + MAX_BLOCK_SIZE as usize;
+            let mut window_buffer = vec![0u8; window_size];
+            let mut decoder = rzstd_decompress::Decoder::new(
+                black_box(&compressed[..]),
+                &mut window_buffer,
+                window_size,
+            );
+            let total = decoder.decode_to_sink().unwrap();
+            assert_eq!(total, expected.len() as u64);
+        })
+    });
+
+    group.finish();
+}
+
+/// Eight distinct bytes in no particular order, so that blocks are made of
+/// many short sequences rather than literals or long matches.
+fn sequence_heavy_input(len: usize) -> Vec<u8> {
+    (0..len as u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 29) as u8)
+        .collect()
+}
+
+/// Executing sequences, the loop slicing literals unchecked once a block's
+/// lengths are validated.
+fn bench_sequences(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Sequences");
+
+    let expected = sequence_heavy_input(16 * 1024 * 1024);
+    let compressed = zstd::encode_all(&expected[..], 3).unwrap();
+
+    group.throughput(Throughput::Bytes(expected.len() as u64));
+    group.bench_function("rzstd (sink)", |b| {
+        b.iter(|| {
+            let total = rzstd_decompress::Decoder::new_auto(black_box(&compressed[..]))
+                .decode_to_sink()
+                .unwrap();
+            assert_eq!(total, expected.len() as u64);
+        })
+    });
+
+    group.finish();
+}
+
+/// Runs of patterns 1 to 15 bytes long, so that matches mostly overlap
+/// their own output at offsets shorter than a copy word.
+fn short_offset_input(len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len);
+    for period in (1..16).cycle() {
+        if out.len() >= len {
+            break;
+        }
+        out.extend((0..4096).map(|i| (period * 16 + i % period) as u8));
+    }
+    out.truncate(len);
+    out
+}
+
+/// Copying matches through a repeated pattern rather than byte by byte.
+fn bench_short_offsets(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Short Offsets");
+
+    let expected = short_offset_input(16 * 1024 * 1024);
+    let compressed = zstd::encode_all(&expected[..], 1).unwrap();
+
+    group.throughput(Throughput::Bytes(expected.len() as u64));
+    group.bench_function("rzstd (sink)", |b| {
+        b.iter(|| {
+            let total = rzstd_decompress::Decoder::new_auto(black_box(&compressed[..]))
+                .decode_to_sink()
+                .unwrap();
+            assert_eq!(total, expected.len() as u64);
+        })
+    });
+
+    group.finish();
+}
+
+/// The cost of hashing the output of a frame that carries a checksum, with
+/// the output itself discarded so that hashing makes up most of the
+/// difference.
+fn bench_checksum(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Checksum");
+
+    let expected = b"checksum ".repeat(8 * 1024 * 1024);
+    let compressed = {
+        let mut encoder = zstd::Encoder::new(Vec::new(), 3).unwrap();
+        encoder.include_checksum(true).unwrap();
+        std::io::Write::write_all(&mut encoder, &expected).unwrap();
+        encoder.finish().unwrap()
+    };
+
+    group.throughput(Throughput::Bytes(expected.len() as u64));
+    for (name, skip) in [("verified", false), ("skipped", true)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let total = rzstd_decompress::DecoderBuilder::new()
+                    .source(black_box(&compressed[..]))
+                    .skip_checksum(skip)
+                    .build()
+                    .decode_to_sink()
+                    .unwrap();
+                assert_eq!(total, expected.len() as u64);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// Many tiny frames, whose headers are parsed a few bytes at a time.
+fn bench_small_reads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Small Reads");
+
+    let frame = zstd::encode_all(&b"a tiny frame"[..], 3).unwrap();
+    let compressed = frame.repeat(10_000);
+    let expected = b"a tiny frame".repeat(10_000);
+
+    // Reading from a file turns every read call into a syscall.
+    let path = std::env::temp_dir().join("rzstd_small_reads.zst");
+    std::fs::write(&path, &compressed).unwrap();
+
+    group.throughput(Throughput::Bytes(compressed.len() as u64));
+    group.bench_function("unbuffered", |b| {
+        b.iter(|| {
+            let file = std::fs::File::open(&path).unwrap();
+            let mut output_buffer = Vec::with_capacity(expected.len());
+            rzstd_decompress::Decoder::new_auto(black_box(file))
+                .decode(&mut output_buffer)
+                .unwrap();
+            assert_eq!(output_buffer, expected);
+        })
+    });
+    group.bench_function("buffered", |b| {
+        b.iter(|| {
+            let file = std::fs::File::open(&path).unwrap();
+            let mut output_buffer = Vec::with_capacity(expected.len());
+            rzstd_decompress::Decoder::new_buffered(black_box(file))
+                .decode(&mut output_buffer)
+                .unwrap();
+            assert_eq!(output_buffer, expected);
+        })
+    });
+
+    group.finish();
+    std::fs::remove_file(&path).unwrap();
+}
+
+criterion_group!(
+    benches,
+    bench_silesia_corpus,
+    bench_literals,
+    bench_sequences,
+    bench_short_offsets,
+    bench_checksum,
+    bench_small_reads
+);
 criterion_main!(benches);
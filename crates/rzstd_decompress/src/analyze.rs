@@ -0,0 +1,195 @@
+use crate::{decoder::Decoder, errors::Error};
+
+/// Statistics gathered over the decoded content of a zstd stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    /// How many times each byte value occurs in the decoded content.
+    pub histogram: [u64; 256],
+    pub total_len: u64,
+    pub frames: u64,
+    pub blocks: u64,
+}
+
+impl Analysis {
+    /// The order-0 Shannon entropy of the decoded content, in bits per byte.
+    /// A lower bound on what a compressor treating bytes independently can
+    /// achieve.
+    pub fn entropy(&self) -> f64 {
+        if self.total_len == 0 {
+            return 0.0;
+        }
+
+        let total = self.total_len as f64;
+        self.histogram
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+}
+
+/// Decodes every frame in `src`, counting byte values as they are produced
+/// instead of retaining the output.
+pub fn analyze(src: &[u8]) -> Result<Analysis, Error> {
+    let mut decoder = Decoder::new_auto(src);
+    let mut sink = HistogramSink([0; 256]);
+
+    let mut frames = 0;
+    while decoder.decode_one_frame(&mut sink)?.is_some() {
+        frames += 1;
+    }
+
+    let histogram = sink.0;
+    Ok(Analysis {
+        histogram,
+        total_len: histogram.iter().sum(),
+        frames,
+        blocks: decoder.blocks(),
+    })
+}
+
+/// Frame and size totals of a zstd stream, as found by decoding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub frame_count: usize,
+    pub total_output: u64,
+    pub total_input: u64,
+    /// Whether any frame carries a checksum, all of which were verified.
+    pub any_checksum: bool,
+}
+
+/// Decodes every frame in `src`, only counting the output. Unlike
+/// [crate::frame_boundaries], this validates the content, and finds the
+/// output size of frames that don't declare it.
+pub fn summarize(src: &[u8]) -> Result<Summary, Error> {
+    let mut decoder = Decoder::new_auto(src);
+    let mut sink = CountingSink(0);
+
+    let mut summary = Summary {
+        frame_count: 0,
+        total_output: 0,
+        total_input: 0,
+        any_checksum: false,
+    };
+    while let Some(read) = decoder.decode_one_frame(&mut sink)? {
+        summary.frame_count += 1;
+        summary.total_input += read;
+        summary.any_checksum |= decoder.checksummed();
+    }
+
+    summary.total_output = sink.0;
+    Ok(summary)
+}
+
+/// Input and output sizes of a single frame, see [Decoder::decode_with_stats].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Input the frame spanned, along with any skippable frames before it.
+    pub compressed: u64,
+    pub decompressed: u64,
+}
+
+impl FrameStats {
+    /// How many times larger the output is than the input.
+    pub fn ratio(&self) -> f64 {
+        self.decompressed as f64 / self.compressed as f64
+    }
+}
+
+struct CountingSink(u64);
+
+impl std::io::Write for CountingSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct HistogramSink([u64; 256]);
+
+impl std::io::Write for HistogramSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for &byte in buf {
+            self.0[byte as usize] += 1;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() -> Result<(), Error> {
+        let first = zstd::encode_all(&b"summary".repeat(10_000)[..], 3)?;
+        let second = {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.include_checksum(true)?;
+            std::io::Write::write_all(&mut encoder, b"checked")?;
+            encoder.finish()?
+        };
+
+        let src = [first.as_slice(), &second].concat();
+        assert_eq!(
+            summarize(&src)?,
+            Summary {
+                frame_count: 2,
+                total_output: 70_007,
+                total_input: src.len() as u64,
+                any_checksum: true,
+            }
+        );
+
+        assert!(!summarize(&first)?.any_checksum);
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze() -> Result<(), Error> {
+        let content = b"abracadabra".repeat(20_000);
+        let src = [
+            zstd::encode_all(&content[..], 3)?,
+            zstd::encode_all(&b"zz"[..], 3)?,
+        ]
+        .concat();
+
+        let analysis = analyze(&src)?;
+        assert_eq!(analysis.frames, 2);
+        assert_eq!(analysis.total_len, content.len() as u64 + 2);
+        assert_eq!(analysis.histogram.iter().sum::<u64>(), analysis.total_len);
+        assert_eq!(analysis.histogram[b'a' as usize], 5 * 20_000);
+        assert_eq!(analysis.histogram[b'z' as usize], 2);
+        // 220 KB can't fit in a single 128 KiB block.
+        assert!(analysis.blocks >= 3, "blocks={}", analysis.blocks);
+
+        let empty = analyze(&[])?;
+        assert_eq!((empty.frames, empty.total_len), (0, 0));
+        assert_eq!(empty.entropy(), 0.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_entropy() -> Result<(), Error> {
+        let uniform = (0..=255u8).collect::<Vec<_>>().repeat(4);
+        let analysis = analyze(&zstd::encode_all(&uniform[..], 3)?)?;
+        assert!((analysis.entropy() - 8.0).abs() < 1e-9);
+
+        let constant = analyze(&zstd::encode_all(&[7; 1000][..], 3)?)?;
+        assert_eq!(constant.entropy(), 0.0);
+
+        Ok(())
+    }
+}
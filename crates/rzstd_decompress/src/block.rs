@@ -1,10 +1,23 @@
-use crate::{MAX_BLOCK_SIZE, context::Context, prelude::*};
+use crate::{MAX_BLOCK_SIZE, context::Context, prelude::*, sequences_section::Sequence};
 pub const HEADER_SIZE: usize = 3;
 
 impl<R: rzstd_io::Reader> Context<'_, R> {
-    pub fn block(&mut self) -> Result<bool, Error> {
+    pub fn block(&mut self, on_seq: &mut impl FnMut(&Sequence)) -> Result<bool, Error> {
         let header = Header::read(&mut self.src)?;
-        tracing::debug!("decoding block (type={:?})", header.block_type());
+        let _block = tracing::debug_span!(
+            "block",
+            ty = ?header.block_type(),
+            size = header.content_size()
+        )
+        .entered();
+
+        // Raw and RLE blocks regenerate their whole size at once, which
+        // can't exceed the window.
+        if let Some(size) = header.decompressed_size()
+            && size as usize > self.window_buf.size()
+        {
+            return Err(Error::BlockSizeOutOfBounds(size));
+        }
 
         match header.block_type() {
             Type::Raw => {
@@ -16,23 +29,39 @@ impl<R: rzstd_io::Reader> Context<'_, R> {
                 let count = header.decompressed_size().ok_or(Error::MissingBlockSize)?;
                 let byte = self.src.read_u8()?;
                 tracing::debug!("block size={}", count);
-                self.window_buf.push_rle(byte, count as usize);
+                self.window_buf.push_rle(byte, count as usize)?;
             }
             Type::Compressed => {
-                let read = self.literals_section()? as usize;
-
-                tracing::debug!(
-                    "literals.len={:?}; literals={:?}",
-                    self.literals_buf[..self.literals_idx].len(),
-                    &self.literals_buf[..self.literals_idx]
-                );
-
-                self.sequence_section(header.content_size() as usize - read)?;
+                // Keep the literals and sequences sections from reading into
+                // the next block.
+                self.src.set_limit(header.content_size() as u64);
+                let res = self.compressed_block(header.content_size(), on_seq);
+                self.src.set_limit(u64::MAX);
+                res?;
             }
         }
 
         Ok(header.last_block())
     }
+
+    fn compressed_block(
+        &mut self,
+        block_size: u32,
+        on_seq: &mut impl FnMut(&Sequence),
+    ) -> Result<(), Error> {
+        let read = self.block_literals(Some(block_size))? as usize;
+
+        tracing::debug!(
+            "literals.len={:?}; literals={:?}",
+            self.literals_buf[..self.literals_idx].len(),
+            &self.literals_buf[..self.literals_idx]
+        );
+
+        let seq_size = (block_size as usize)
+            .checked_sub(read)
+            .ok_or(Error::Corruption)?;
+        self.sequence_section(seq_size, on_seq)
+    }
 }
 
 /// The Block Header contains information about the block type and size.
@@ -52,9 +81,12 @@ impl Header {
 
         let last_block = raw & 0x01 != 0;
 
-        let block_type = {
-            let block_type = ((raw >> 1) & 0x03) as u8;
-            Type::try_from(TwoBitFlag::from_u8(block_type))?
+        let flag = TwoBitFlag::try_from_u8(((raw >> 1) & 0x03) as u8);
+        let block_type = match flag.ok_or(Error::Corruption)? {
+            TwoBitFlag::Zero => Type::Raw,
+            TwoBitFlag::One => Type::RLE,
+            TwoBitFlag::Two => Type::Compressed,
+            TwoBitFlag::Three => return Err(Error::ReservedBlock(raw)),
         };
 
         let block_size = raw >> 3;
@@ -96,6 +128,7 @@ impl Header {
 }
 
 #[derive(Clone, Copy, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 /// The type of the block
 pub enum Type {
     /// An uncompressed block. [TODO BlockContent] contains
@@ -123,15 +156,111 @@ impl Type {
     }
 }
 
-impl TryFrom<TwoBitFlag> for Type {
-    type Error = Error;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn try_from(flag: TwoBitFlag) -> Result<Self, Self::Error> {
-        match flag {
-            TwoBitFlag::Zero => Ok(Self::Raw),
-            TwoBitFlag::One => Ok(Self::RLE),
-            TwoBitFlag::Two => Ok(Self::Compressed),
-            TwoBitFlag::Three => Err(Error::ReservedBlock),
-        }
+    #[test]
+    fn test_reserved_block_type() {
+        // Last block of 100 bytes, with block type 3.
+        let raw: u32 = 1 | (3 << 1) | (100 << 3);
+        let src = raw.to_le_bytes();
+
+        assert!(matches!(
+            Header::read(&mut &src[..HEADER_SIZE]),
+            Err(Error::ReservedBlock(header)) if header == raw
+        ));
+    }
+
+    fn is_eof(res: Result<bool, Error>) -> bool {
+        matches!(
+            res,
+            Err(Error::IO(rzstd_io::Error::IO(e)))
+                if e.kind() == std::io::ErrorKind::UnexpectedEof
+        )
+    }
+
+    /// A compressed block of `block_size` bytes holding a raw literals section
+    /// of `literals.len()` bytes and no sequences.
+    fn literals_only_block(block_size: u32, literals: &[u8]) -> Vec<u8> {
+        let raw = 1 | (2 << 1) | (block_size << 3);
+        let literals_header = (literals.len() as u8) << 3;
+
+        [
+            &raw.to_le_bytes()[..HEADER_SIZE],
+            &[literals_header],
+            literals,
+            &[0],
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_compressed_block() -> Result<(), Error> {
+        let src = literals_only_block(6, &[1, 2, 3, 4]);
+
+        let mut buf = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+        let mut ctx = Context::new(src.as_slice(), &mut buf, MAX_BLOCK_SIZE as usize);
+        assert!(ctx.block(&mut |_| {})?);
+
+        assert_eq!(ctx.window_buf.as_slice(), [1, 2, 3, 4]);
+        assert_eq!(ctx.src.remaining(), u64::MAX);
+        Ok(())
+    }
+
+    #[test]
+    fn test_literals_only_block() -> Result<(), Error> {
+        let literals = (0..=20).collect::<Vec<u8>>();
+        let src = [
+            literals_only_block(23, &literals[..21]),
+            literals_only_block(6, &literals[..4]),
+        ]
+        .concat();
+
+        let mut buf = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+        let mut ctx = Context::new(src.as_slice(), &mut buf, MAX_BLOCK_SIZE as usize);
+        assert!(ctx.block(&mut |_| {})?);
+        assert!(ctx.block(&mut |_| {})?);
+        assert_eq!(ctx.literals_idx, 0);
+        assert_eq!(
+            ctx.window_buf.as_slice(),
+            [&literals[..21], &literals[..4]].concat()
+        );
+
+        // The only byte after the literals must be an empty sequences header.
+        let mut src = literals_only_block(6, &[1, 2, 3, 4]);
+        *src.last_mut().unwrap() = 1;
+        let mut ctx = Context::new(src.as_slice(), &mut buf, MAX_BLOCK_SIZE as usize);
+        assert!(ctx.block(&mut |_| {}).is_err());
+
+        // Nor may the literals outgrow the window.
+        let src = literals_only_block(22, &[0; 20]);
+        let mut ctx = Context::new(src.as_slice(), &mut buf, 16);
+        ctx.reset(16)?;
+        assert!(matches!(
+            ctx.block(&mut |_| {}),
+            Err(Error::BlockSizeOutOfBounds(20))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_shorter_than_contents() {
+        // The literals section alone spans 5 of the block's 2 bytes.
+        let src = literals_only_block(2, &[1, 2, 3, 4]);
+
+        let mut buf = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+        let mut ctx = Context::new(src.as_slice(), &mut buf, MAX_BLOCK_SIZE as usize);
+        assert!(is_eof(ctx.block(&mut |_| {})));
+    }
+
+    #[test]
+    fn test_block_longer_than_source() {
+        // The block claims 100 bytes, but the input ends after 6.
+        let src = literals_only_block(100, &[1, 2, 3, 4]);
+
+        let mut buf = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+        let mut ctx = Context::new(src.as_slice(), &mut buf, MAX_BLOCK_SIZE as usize);
+        assert!(is_eof(ctx.block(&mut |_| {})));
     }
 }
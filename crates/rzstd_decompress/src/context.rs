@@ -1,12 +1,29 @@
+use rzstd_io::TakeExact;
+
 use crate::{
-    LL_DIST, MAX_BLOCK_SIZE, ML_DIST, OF_DIST, sequences_section::Sequence,
+    Error, LL_DIST, MAX_BLOCK_SIZE, ML_DIST, OF_DIST,
+    dictionary::Dictionary,
+    sequences_section::{RepeatOffsets, Sequence},
     window::Window,
 };
 
+/// Size of [Context::literals_buf]. A block's literals regenerate at most
+/// [MAX_BLOCK_SIZE] bytes, and every literals section is checked against this
+/// before being decoded into the buffer.
+pub const LITERALS_BUF_SIZE: usize = MAX_BLOCK_SIZE as usize;
+
+/// The repeat offsets frames without a dictionary start with.
+pub const DEFAULT_OFFSETS: [usize; 3] = [1, 4, 8];
+
 pub struct Context<'out, R: rzstd_io::Reader> {
-    pub src: R,
+    /// Unbounded, except while a compressed block is being decoded, when it
+    /// is limited to the block's size.
+    pub src: TakeExact<R>,
     pub window_buf: Window<'out>,
 
+    /// Block-sized buffers, allocated once in [Context::new] and reused for
+    /// every block and frame. They are only ever sliced, never resized; the
+    /// `*_idx` fields track how much of each holds the current block's data.
     pub literals_buf: Vec<u8>,
     pub literals_idx: usize,
 
@@ -15,17 +32,36 @@ pub struct Context<'out, R: rzstd_io::Reader> {
 
     pub huff: HuffContext,
     pub fse: FSEContext,
-    pub offset_hist: [usize; 3],
+    pub offset_hist: RepeatOffsets,
+    /// What [Context::offset_hist] starts out as in every frame.
+    pub initial_offsets: [usize; 3],
+    /// Seeds the tables and repeat offsets of every frame, in place of
+    /// [Context::initial_offsets].
+    pub dictionary: Option<Dictionary>,
+    /// Ceiling on the accuracy log of FSE tables read from the stream, on top
+    /// of each table's own limit.
+    pub max_fse_accuracy_log: u8,
+    /// Most sequences a block may declare, on top of what
+    /// [Context::sequences_buf] holds.
+    pub max_sequences: usize,
+    /// Window position past which the rest of a block's sequences are
+    /// skipped, for frames only partly decoded. Lifted at every frame.
+    pub output_cap: u64,
 
+    /// Holds a block's compressed literals or sequences while they're decoded.
     pub scratch_buf: Vec<u8>,
 }
 
 impl<'out, R: rzstd_io::Reader> Context<'out, R> {
     pub fn new(src: R, dst: &'out mut [u8], window_size: usize) -> Self {
+        Self::with_window(src, Window::new(dst, window_size))
+    }
+
+    pub fn with_window(src: R, window_buf: Window<'out>) -> Self {
         Self {
-            src,
-            window_buf: Window::new(dst, window_size),
-            literals_buf: vec![0; MAX_BLOCK_SIZE as usize],
+            src: TakeExact::new(src, u64::MAX),
+            window_buf,
+            literals_buf: vec![0; LITERALS_BUF_SIZE],
             literals_idx: 0,
             sequences_buf: vec![Sequence::default(); MAX_BLOCK_SIZE as usize],
             sequences_idx: 0,
@@ -35,16 +71,48 @@ impl<'out, R: rzstd_io::Reader> Context<'out, R> {
                 ml: None,
                 of: None,
             },
-            offset_hist: [1, 4, 8],
+            offset_hist: RepeatOffsets(DEFAULT_OFFSETS),
+            initial_offsets: DEFAULT_OFFSETS,
+            dictionary: None,
+            max_fse_accuracy_log: u8::MAX,
+            max_sequences: MAX_BLOCK_SIZE as usize,
+            output_cap: u64::MAX,
             scratch_buf: vec![0; MAX_BLOCK_SIZE as usize],
         }
     }
 
-    pub fn reset(&mut self, window_size: usize) {
-        self.window_buf.reset(window_size);
+    /// Prepares the context for a new frame.
+    ///
+    /// The literals, sequences and scratch buffers keep their allocations;
+    /// only the indices into them are rewound, so decoding many frames in a
+    /// row doesn't reallocate.
+    pub fn reset(&mut self, window_size: usize) -> Result<(), Error> {
+        self.window_buf.reset(window_size)?;
+        self.rewind();
+        Ok(())
+    }
 
+    /// Prepares the context for a new single-segment frame of `content_size`
+    /// bytes (see [Window::reset_single_segment]).
+    pub fn reset_single_segment(&mut self, content_size: usize) -> Result<(), Error> {
+        self.window_buf.reset_single_segment(content_size)?;
+        self.rewind();
+        Ok(())
+    }
+
+    fn rewind(&mut self) {
         self.literals_idx = 0;
         self.sequences_idx = 0;
+        self.output_cap = u64::MAX;
+
+        if let Some(dict) = &self.dictionary {
+            self.huff = HuffContext {
+                table: dict.huff.clone(),
+            };
+            self.fse = dict.fse.clone();
+            self.offset_hist = RepeatOffsets(dict.offsets);
+            return;
+        }
 
         self.huff = HuffContext { table: None };
         self.fse = FSEContext {
@@ -52,7 +120,7 @@ impl<'out, R: rzstd_io::Reader> Context<'out, R> {
             ml: None,
             of: None,
         };
-        self.offset_hist = [1, 4, 8];
+        self.offset_hist = RepeatOffsets(self.initial_offsets);
     }
 }
 
@@ -61,7 +129,7 @@ pub struct HuffContext {
     pub table: Option<rzstd_huff0::DecodingTable>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FSEContext {
     pub ll: Option<rzstd_fse::DecodingTable<{ LL_DIST.table_size() }>>,
     pub ml: Option<rzstd_fse::DecodingTable<{ ML_DIST.table_size() }>>,
@@ -78,6 +146,11 @@ impl<R: std::io::Read + std::fmt::Debug> std::fmt::Debug for Context<'_, R> {
             .field("huff", &self.huff)
             .field("fse", &self.fse)
             .field("offset_hist", &self.offset_hist)
+            .field("initial_offsets", &self.initial_offsets)
+            .field("dictionary", &self.dictionary)
+            .field("max_fse_accuracy_log", &self.max_fse_accuracy_log)
+            .field("max_sequences", &self.max_sequences)
+            .field("output_cap", &self.output_cap)
             .field("scratch_buf", &self.scratch_buf)
             .finish()
     }
@@ -1,76 +1,2335 @@
+use std::{
+    collections::VecDeque,
+    io::{BufReader, Read},
+    num::NonZeroUsize,
+    ops::Range,
+};
+
+use rzstd_io::{CountingReader, ReadU32, TakeExact};
 use xxhash_rust::xxh64::Xxh64;
 
-use crate::{MAGIC_NUM, context::Context, errors::Error, frame};
+use crate::{
+    FrameStats, MAGIC_NUM, MAX_BLOCK_SIZE, MIN_WINDOW_SIZE, WindowStore,
+    context::Context, dictionary::Dictionary, errors::Error, frame,
+    sequences_section::Sequence, window::Window,
+};
 
 pub struct Decoder<'b, R: rzstd_io::Reader> {
-    ctx: Context<'b, R>,
+    ctx: Context<'b, CountingReader<R>>,
     checksum: Xxh64,
+    flush_chunk: usize,
+    strict_trailing: bool,
+    strict_unused_bit: bool,
+    strict_verify: bool,
+    /// Bytes handed to the output so far, and how many it may receive in
+    /// total, across all frames.
+    output: u64,
+    output_limit: Option<u64>,
+    /// Blocks decoded so far, across all frames.
+    blocks: u64,
+    /// Frames started so far, and the span the current one's blocks are
+    /// decoded in.
+    frames: u64,
+    frame_span: tracing::Span,
+    /// Whether the last frame decoded carried a checksum.
+    checksummed: bool,
+    skip_checksum: bool,
+    /// The checksum the last frame was verified against.
+    last_checksum: Option<u32>,
+    /// History the next frame starts with, see [Decoder::set_prefix].
+    prefix: Option<Vec<u8>>,
+    /// Fed all of the output, see [Decoder::with_extra_hasher].
+    extra_hasher: Option<Box<dyn std::hash::Hasher + Send>>,
+    /// How far [Decoder::decode_lossy] looks for the next frame.
+    max_resync: usize,
+}
+
+const DEFAULT_FLUSH_CHUNK: usize = 64 * 1024;
+const DEFAULT_MAX_RESYNC: usize = 16 << 20;
+
+impl<R: rzstd_io::Reader> Decoder<'static, R> {
+    /// Creates a decoder managing its own window buffer, sized from each
+    /// frame's header.
+    ///
+    /// The buffer starts small and grows only as output is produced, so small
+    /// frames declaring a large window don't pay for the full allocation.
+    pub fn new_auto(src: R) -> Self {
+        DecoderBuilder::new().source(src).build()
+    }
 }
 
-const CHUNK: usize = 64 * 1024;
+impl<R: std::io::Read + std::fmt::Debug> Decoder<'static, BufReader<R>> {
+    /// Like [Decoder::new_auto], but reads `src` through an internal buffer
+    /// holding at least one block.
+    ///
+    /// Header parsing issues many tiny reads, which are costly on unbuffered
+    /// sources like files, sockets or pipes.
+    pub fn new_buffered(src: R) -> Self {
+        Self::new_auto(BufReader::with_capacity(MAX_BLOCK_SIZE as usize, src))
+    }
+}
 
 impl<'b, R: rzstd_io::Reader> Decoder<'b, R> {
     pub fn new(src: R, dst: &'b mut [u8], window_size: usize) -> Self {
-        Decoder {
-            ctx: Context::new(src, dst, window_size),
+        DecoderBuilder::new()
+            .source(src)
+            .window_buffer(dst, window_size)
+            .build()
+    }
+
+    fn with_context(ctx: Context<'b, CountingReader<R>>) -> Self {
+        Self {
+            ctx,
             checksum: Xxh64::new(0),
+            flush_chunk: DEFAULT_FLUSH_CHUNK,
+            strict_trailing: false,
+            strict_unused_bit: false,
+            strict_verify: false,
+            output: 0,
+            output_limit: None,
+            blocks: 0,
+            frames: 0,
+            frame_span: tracing::Span::none(),
+            checksummed: false,
+            skip_checksum: false,
+            last_checksum: None,
+            prefix: None,
+            extra_hasher: None,
+            max_resync: DEFAULT_MAX_RESYNC,
+        }
+    }
+
+    /// Sets how many decoded bytes are accumulated before being written out,
+    /// 64 KiB by default. The last block of a frame is always flushed.
+    pub fn with_flush_chunk(mut self, bytes: NonZeroUsize) -> Self {
+        self.flush_chunk = bytes.get();
+        self
+    }
+
+    /// Whether 1 to 3 bytes left after the last frame, too few to hold a magic
+    /// number, are reported as [Error::TrailingGarbage] rather than ignored.
+    pub fn with_strict_trailing(mut self, strict: bool) -> Self {
+        self.strict_trailing = strict;
+        self
+    }
+
+    /// Whether frames with the header descriptor's unused bit set are rejected
+    /// with [Error::UnusedBitSet] rather than decoded.
+    pub fn with_strict_unused_bit(mut self, strict: bool) -> Self {
+        self.strict_unused_bit = strict;
+        self
+    }
+
+    /// Whether [Decoder::verify] stops at frames without a checksum, which it
+    /// can't check, rather than take them as valid.
+    pub fn with_strict_verify(mut self, strict: bool) -> Self {
+        self.strict_verify = strict;
+        self
+    }
+
+    /// Whether to skip hashing the output of frames carrying a checksum, and
+    /// so verifying it, saving the cost of XXH64 on large inputs. The
+    /// checksum bytes are still consumed, and [Decoder::verify] then has
+    /// nothing to check.
+    pub fn with_skip_checksum(mut self, skip: bool) -> Self {
+        self.skip_checksum = skip;
+        self
+    }
+
+    /// Rejects sequence FSE tables with an accuracy log above `log`, bounding
+    /// each table to `1 << log` entries, with
+    /// [rzstd_fse::Error::AccuracyLogMismatch]. Tables are otherwise only
+    /// capped by the format's own limits.
+    pub fn with_max_fse_accuracy_log(mut self, log: u8) -> Self {
+        self.ctx.max_fse_accuracy_log = log;
+        self
+    }
+
+    /// Rejects blocks declaring more than `n` sequences with
+    /// [Error::Corruption] before decoding any of them, bounding the work a
+    /// single block can cause. Defaults to [MAX_BLOCK_SIZE], as many as a
+    /// block can hold.
+    pub fn with_max_sequences(mut self, n: usize) -> Self {
+        self.ctx.max_sequences = n;
+        self
+    }
+
+    /// Sets how many bytes past a bad frame's start [Decoder::decode_lossy]
+    /// scans for the next frame before giving up, 16 MiB by default.
+    pub fn with_max_resync(mut self, bytes: usize) -> Self {
+        self.max_resync = bytes;
+        self
+    }
+
+    /// Feeds all of the output, across frames, into `hasher` as it is
+    /// produced, so that a second integrity check (a CRC32 or SHA, say) can be
+    /// computed alongside decoding rather than in another pass over the
+    /// output. See [Decoder::extra_digest].
+    pub fn with_extra_hasher(
+        mut self,
+        hasher: impl std::hash::Hasher + Send + 'static,
+    ) -> Self {
+        self.extra_hasher = Some(Box::new(hasher));
+        self
+    }
+
+    /// What the hasher given to [Decoder::with_extra_hasher] computed over the
+    /// output so far, if there is one.
+    pub fn extra_digest(&self) -> Option<u64> {
+        self.extra_hasher.as_ref().map(|hasher| hasher.finish())
+    }
+
+    /// Makes `prefix` the history the next frame starts with, so that its
+    /// matches can reach into it, as in frames compressed against a raw
+    /// content prefix. Only the last window size bytes of it are kept.
+    ///
+    /// Frames can be chained by seeding each one with the
+    /// [Decoder::window_tail] of the previous.
+    pub fn set_prefix(&mut self, prefix: &[u8]) {
+        self.prefix = Some(prefix.to_vec());
+    }
+
+    /// Up to the last `n` bytes of history the last frame left in the window:
+    /// its output, after any prefix it started with, and at most the frame's
+    /// window size.
+    pub fn window_tail(&self, n: usize) -> &[u8] {
+        let window = &self.ctx.window_buf;
+        let len = n.min(window.index()).min(window.size());
+        &window.as_slice()[window.index() - len..]
+    }
+
+    /// Consumes the decoder, returning all of the history the last frame left
+    /// in the window. See [Decoder::window_tail].
+    pub fn into_history(self) -> Vec<u8> {
+        self.window_tail(usize::MAX).to_vec()
+    }
+
+    /// Loads `dict` for every frame starting after this call to be decoded
+    /// with, until [Decoder::clear_dictionary]: each one starts with its
+    /// content as history, and with its entropy tables and repeat offsets, so
+    /// that even blocks of later frames can repeat them.
+    ///
+    /// `dict` is parsed as a zstd dictionary if it starts with its magic
+    /// number, and taken as raw content otherwise. Frames asking for another
+    /// dictionary ID fail with [Error::WrongDictionary].
+    pub fn set_dictionary(&mut self, dict: &[u8]) -> Result<(), Error> {
+        self.ctx.dictionary = Some(Dictionary::read(dict)?);
+        Ok(())
+    }
+
+    /// Drops the dictionary loaded by [Decoder::set_dictionary], so frames
+    /// starting after this call are decoded without one.
+    pub fn clear_dictionary(&mut self) {
+        self.ctx.dictionary = None;
+    }
+
+    /// Sets the repeat offsets every frame starting after this call begins
+    /// with, instead of `[1, 4, 8]`. Fails with [Error::ZeroOffset] if any of
+    /// them is zero.
+    pub fn set_initial_offsets(&mut self, offsets: [u32; 3]) -> Result<(), Error> {
+        if offsets.contains(&0) {
+            return Err(Error::ZeroOffset);
         }
+
+        self.ctx.initial_offsets = offsets.map(|offset| offset as usize);
+        Ok(())
     }
 
     pub fn decode(&mut self, mut writer: impl std::io::Write) -> Result<(), Error> {
-        while self.decode_frame(&mut writer)? {}
+        let flush_chunk = self.flush_chunk;
+        let mut write = |data: &[u8]| writer.write_all(data).map_err(Error::from);
+
+        while self.decode_frame(&mut write, flush_chunk, &mut ignore_sequence)? {}
         Ok(())
     }
 
-    fn decode_frame(&mut self, writer: &mut impl std::io::Write) -> Result<bool, Error> {
-        let magic_num = match self.ctx.src.read_u32() {
-            Ok(it) => it,
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
-            Err(e) => return Err(Error::from(e)),
+    /// Like [Decoder::decode], but writes each chunk through as many
+    /// [std::io::Write::write] calls as `writer` takes, handing the total
+    /// number of bytes written so far to `on_progress` after every one.
+    pub fn decode_with_progress(
+        &mut self,
+        mut writer: impl std::io::Write,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<(), Error> {
+        let flush_chunk = self.flush_chunk;
+        let mut written = 0u64;
+        let mut write = |mut data: &[u8]| {
+            while !data.is_empty() {
+                match writer.write(data) {
+                    Ok(0) => {
+                        let err = std::io::Error::from(std::io::ErrorKind::WriteZero);
+                        return Err(err.into());
+                    }
+                    Ok(n) => {
+                        data = &data[n..];
+                        written += n as u64;
+                        on_progress(written);
+                    }
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            Ok(())
         };
-        if magic_num != MAGIC_NUM {
-            return Err(Error::InvalidMagicNum(magic_num));
+
+        while self.decode_frame(&mut write, flush_chunk, &mut ignore_sequence)? {}
+        Ok(())
+    }
+
+    /// Decodes every frame into `out`, replacing its contents but keeping its
+    /// allocation, so decoding many inputs into the same vector doesn't
+    /// reallocate once it is large enough.
+    pub fn decode_into_vec(&mut self, out: &mut Vec<u8>) -> Result<(), Error> {
+        out.clear();
+
+        let flush_chunk = self.flush_chunk;
+        let mut extend = |data: &[u8]| {
+            out.extend_from_slice(data);
+            Ok(())
+        };
+        while self.decode_frame(&mut extend, flush_chunk, &mut ignore_sequence)? {}
+        Ok(())
+    }
+
+    /// Decodes every frame, discarding the output and returning only its total
+    /// size, so that throughput measurements leave out the cost of storing
+    /// it.
+    pub fn decode_to_sink(&mut self) -> Result<u64, Error> {
+        let mut total = 0;
+        let mut count = |data: &[u8]| {
+            total += data.len() as u64;
+            Ok(())
+        };
+        while self.decode_frame(&mut count, self.flush_chunk, &mut ignore_sequence)? {}
+        Ok(total)
+    }
+
+    /// Decodes every frame, handing each block's output to `on_output` as
+    /// soon as it is produced.
+    ///
+    /// Nothing beyond the window is kept around, so memory stays bounded by
+    /// the window buffer however large the output grows.
+    pub fn decode_streaming(
+        &mut self,
+        mut on_output: impl FnMut(&[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        while self.decode_frame(&mut on_output, 1, &mut ignore_sequence)? {}
+        Ok(())
+    }
+
+    /// Decodes every frame, handing the output to `on_frame` in pieces of
+    /// exactly `frame_size` bytes, whatever the block and frame boundaries,
+    /// except for the last piece, which may be smaller.
+    ///
+    /// Output is buffered only until a whole piece is available.
+    pub fn decode_paced(
+        &mut self,
+        frame_size: NonZeroUsize,
+        mut on_frame: impl FnMut(&[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let frame_size = frame_size.get();
+        let mut buf = Vec::with_capacity(frame_size);
+        let mut pace = |mut data: &[u8]| {
+            while !data.is_empty() {
+                if buf.is_empty() && data.len() >= frame_size {
+                    let (piece, rest) = data.split_at(frame_size);
+                    on_frame(piece)?;
+                    data = rest;
+                    continue;
+                }
+
+                let n = (frame_size - buf.len()).min(data.len());
+                buf.extend_from_slice(&data[..n]);
+                data = &data[n..];
+                if buf.len() == frame_size {
+                    on_frame(&buf)?;
+                    buf.clear();
+                }
+            }
+            Ok(())
+        };
+        while self.decode_frame(&mut pace, 1, &mut ignore_sequence)? {}
+
+        if !buf.is_empty() {
+            on_frame(&buf)?;
         }
+        Ok(())
+    }
 
-        let frame = frame::Header::read(&mut self.ctx.src)?;
-        let window_size = frame.window_size()? as usize;
+    /// Turns the decoder into an iterator over its output, yielding each
+    /// flushed chunk (see [Decoder::with_flush_chunk]) as an owned buffer.
+    ///
+    /// Blocks are only decoded as chunks are asked for. The iterator ends
+    /// after the last frame, or right after yielding an error.
+    pub fn chunks(mut self) -> impl Iterator<Item = Result<Vec<u8>, Error>> {
+        let mut frame = None;
+        let mut flushed = 0;
+        let mut pending = VecDeque::new();
+        // Output flushed before an error is still handed out first.
+        let mut error = None;
+        let mut done = false;
+
+        std::iter::from_fn(move || {
+            loop {
+                if let Some(chunk) = pending.pop_front() {
+                    return Some(Ok(chunk));
+                }
+                if done {
+                    return error.take().map(Err);
+                }
 
-        self.ctx.reset(window_size);
+                match self.next_chunks(&mut frame, &mut flushed, &mut pending) {
+                    Ok(more) => done = !more,
+                    Err(err) => {
+                        error = Some(err);
+                        done = true;
+                    }
+                }
+            }
+        })
+    }
 
-        let mut flushed_idx = 0;
+    /// Advances [Decoder::chunks] by one block, starting or ending a frame as
+    /// needed, and returns whether there is anything left to decode.
+    fn next_chunks(
+        &mut self,
+        frame: &mut Option<frame::Header>,
+        flushed: &mut u64,
+        pending: &mut VecDeque<Vec<u8>>,
+    ) -> Result<bool, Error> {
+        let Some(header) = frame else {
+            *frame = self.begin_frame()?;
+            *flushed = self.ctx.window_buf.position();
+            return Ok(frame.is_some());
+        };
 
-        loop {
-            let last = self.ctx.block()?;
-            let current_idx = self.ctx.window_buf.index();
+        let header = header.clone();
+        let mut collect = |data: &[u8]| {
+            pending.push_back(data.to_vec());
+            Ok(())
+        };
+        let flush_chunk = self.flush_chunk;
+        if self.decode_block(&mut collect, flushed, flush_chunk, &mut ignore_sequence)? {
+            *frame = None;
+            self.end_frame(&header)?;
+        }
+        Ok(true)
+    }
 
-            if current_idx < flushed_idx {
-                flushed_idx = 0;
+    /// Decodes every frame, buffering its whole output and handing it to
+    /// `on_frame` along with the frame's index before moving on to the next.
+    ///
+    /// The output limit, if any, applies to each frame on its own, bounding
+    /// the buffer.
+    pub fn decode_each_frame(
+        &mut self,
+        mut on_frame: impl FnMut(usize, &[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let flush_chunk = self.flush_chunk;
+        let mut buf = Vec::new();
+
+        for idx in 0.. {
+            self.output = 0;
+            buf.clear();
+
+            let mut collect = |data: &[u8]| {
+                buf.extend_from_slice(data);
+                Ok(())
+            };
+            if !self.decode_frame(&mut collect, flush_chunk, &mut ignore_sequence)? {
+                break;
             }
 
-            let available = current_idx.saturating_sub(flushed_idx);
-            if available >= CHUNK || last {
-                let data = &self.ctx.window_buf.as_slice()[flushed_idx..current_idx];
+            on_frame(idx, &buf)?;
+        }
+
+        Ok(())
+    }
+
+    /// Decodes every frame, handing each sequence to `on_seq` just before it
+    /// is executed, with its offset already resolved against the repeat
+    /// offset history. The decoded bytes themselves are discarded.
+    pub fn decode_sequences(
+        &mut self,
+        mut on_seq: impl FnMut(&Sequence),
+    ) -> Result<(), Error> {
+        let mut discard = |_: &[u8]| Ok(());
+        while self.decode_frame(&mut discard, self.flush_chunk, &mut on_seq)? {}
+        Ok(())
+    }
+
+    /// Decodes a single frame, returning the number of input bytes it spanned,
+    /// or `None` if the source was already exhausted.
+    ///
+    /// Useful when zstd frames are interleaved with other data: the caller
+    /// can advance its own cursor by the returned amount.
+    pub fn decode_one_frame(
+        &mut self,
+        mut writer: impl std::io::Write,
+    ) -> Result<Option<u64>, Error> {
+        let flush_chunk = self.flush_chunk;
+        let mut write = |data: &[u8]| writer.write_all(data).map_err(Error::from);
+
+        let start = self.ctx.src.get_ref().count();
+        if !self.decode_frame(&mut write, flush_chunk, &mut ignore_sequence)? {
+            return Ok(None);
+        }
+
+        Ok(Some(self.ctx.src.get_ref().count() - start))
+    }
 
-                writer.write_all(data).map_err(Error::from)?;
-                self.checksum.update(data);
+    /// Like [Decoder::decode], but hands the input and output sizes of each
+    /// frame to `on_frame` once it is decoded, e.g. to compute per-frame
+    /// compression ratios.
+    pub fn decode_with_stats(
+        &mut self,
+        mut writer: impl std::io::Write,
+        mut on_frame: impl FnMut(FrameStats),
+    ) -> Result<(), Error> {
+        let flush_chunk = self.flush_chunk;
+        let mut write = |data: &[u8]| writer.write_all(data).map_err(Error::from);
 
-                flushed_idx = current_idx;
+        loop {
+            let consumed = self.ctx.src.get_ref().count();
+            let produced = self.output;
+            if !self.decode_frame(&mut write, flush_chunk, &mut ignore_sequence)? {
+                return Ok(());
             }
 
-            if last {
+            on_frame(FrameStats {
+                compressed: self.ctx.src.get_ref().count() - consumed,
+                decompressed: self.output - produced,
+            });
+        }
+    }
+
+    /// Decodes and writes only the first `limit` bytes of output, returning
+    /// how many there were, fewer if the input holds less.
+    ///
+    /// Decoding stops as soon as the limit is reached, even in the middle of
+    /// a block, whose remaining sequences are neither decoded nor executed;
+    /// its literals are still decoded in full, so previewing a huge input
+    /// costs at most one block more than the preview. The rest of the frame is
+    /// left unread, and its checksum unverified, so the decoder shouldn't be
+    /// used any further unless the input ran out first.
+    pub fn decode_prefix(
+        &mut self,
+        mut writer: impl std::io::Write,
+        limit: u64,
+    ) -> Result<u64, Error> {
+        let mut written = 0;
+        while written < limit {
+            let Some(frame) = self.begin_frame()? else {
                 break;
+            };
+
+            let start = self.ctx.window_buf.position();
+            let wanted = limit - written;
+            self.ctx.output_cap = start.saturating_add(wanted);
+
+            let mut flushed = start;
+            let mut write = |data: &[u8]| {
+                let n = data.len().min((limit - written) as usize);
+                writer.write_all(&data[..n])?;
+                written += n as u64;
+                Ok(())
+            };
+            // Every block is flushed, so `flushed` tracks the frame's output.
+            let mut last = false;
+            while !last && flushed - start < wanted {
+                last =
+                    self.decode_block(&mut write, &mut flushed, 1, &mut ignore_sequence)?;
+            }
+
+            // A frame cut short by the limit can't be checked against its
+            // checksum.
+            if last && self.ctx.window_buf.position() - start < wanted {
+                self.end_frame(&frame)?;
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Decodes every frame without keeping any output beyond the window,
+    /// returning whether each frame's checksum matched its contents.
+    ///
+    /// Frames without a checksum are taken as valid, unless
+    /// [Decoder::with_strict_verify] is set, in which case the first such
+    /// frame stops the pass with `Ok(false)`, as it can't be verified.
+    pub fn verify(&mut self) -> Result<bool, Error> {
+        loop {
+            match self.decode_one_frame(std::io::sink()) {
+                Ok(None) => return Ok(true),
+                Ok(Some(_)) if self.strict_verify && !self.checksummed => {
+                    return Ok(false);
+                }
+                Ok(Some(_)) => {}
+                Err(Error::ChecksumMismatch) => return Ok(false),
+                Err(err) => return Err(err),
             }
         }
+    }
+
+    /// The checksum computed over the output of the last frame decoded, which
+    /// matched the one it carried. `None` if that frame has no checksum, if
+    /// checksums are skipped, or before any frame was fully decoded.
+    pub fn last_checksum(&self) -> Option<u32> {
+        self.last_checksum
+    }
+
+    pub(crate) fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    /// Whether the last frame decoded carried a checksum.
+    pub(crate) fn checksummed(&self) -> bool {
+        self.checksummed
+    }
+
+    /// Whether 1 to 3 bytes left after the last frame are an error, see
+    /// [Decoder::with_strict_trailing].
+    pub(crate) fn strict_trailing(&self) -> bool {
+        self.strict_trailing
+    }
+
+    /// The source underneath the decoder's counting and bounding wrappers.
+    pub(crate) fn source_mut(&mut self) -> &mut R {
+        self.ctx.src.get_mut().get_mut()
+    }
+
+    /// Bytes the current frame has produced so far, see
+    /// [Decoder::decode_block].
+    pub(crate) fn frame_position(&self) -> u64 {
+        self.ctx.window_buf.position()
+    }
+
+    fn decode_frame(
+        &mut self,
+        on_output: &mut impl FnMut(&[u8]) -> Result<(), Error>,
+        flush_chunk: usize,
+        on_seq: &mut impl FnMut(&Sequence),
+    ) -> Result<bool, Error> {
+        let Some(frame) = self.begin_frame()? else {
+            return Ok(false);
+        };
+
+        let mut flushed = self.ctx.window_buf.position();
+        while !self.decode_block(on_output, &mut flushed, flush_chunk, on_seq)? {}
+
+        self.end_frame(&frame)?;
+        Ok(true)
+    }
+
+    /// Reads the next frame's header and prepares the context for its
+    /// blocks, or returns `None` if the source is exhausted.
+    pub(crate) fn begin_frame(&mut self) -> Result<Option<frame::Header>, Error> {
+        let Some(magic_num) = self.read_magic_num()? else {
+            return Ok(None);
+        };
+        if magic_num != MAGIC_NUM {
+            return Err(Error::InvalidMagicNum(magic_num));
+        }
+
+        let frame = frame::Header::read(&mut self.ctx.src).map_err(Error::mid_frame)?;
+        if self.strict_unused_bit && frame.has_unused_bit() {
+            return Err(Error::UnusedBitSet);
+        }
+        self.checksum.reset(0);
+        self.checksummed = frame.has_checksum();
+        self.last_checksum = None;
+
+        let window_size = frame.window_size()? as usize;
+        self.frame_span = tracing::info_span!("frame", index = self.frames, window_size);
+        self.frames += 1;
+        let _frame = self.frame_span.clone().entered();
+        tracing::debug!("frame dictionary_id={:?}", frame.dictionary_id());
+
+        let dictionary = self.ctx.dictionary.as_ref();
+        if let Some(id) = frame.dictionary_id().filter(|&id| id != 0)
+            && let Some(loaded) = dictionary.map(|dict| dict.id).filter(|&id| id != 0)
+            && id != loaded
+        {
+            return Err(Error::WrongDictionary { frame: id, loaded });
+        }
+
+        // A prefix, or the dictionary content it takes precedence over, comes
+        // on top of the content, so it can't share its segment. Matches may
+        // reach all of the dictionary content on top of the window, as with
+        // the reference decoder.
+        let prefix = self.prefix.take();
+        let dict_content = match (&prefix, dictionary) {
+            (None, Some(dict)) => dict.content.len(),
+            _ => 0,
+        };
+        if frame.is_single_segment() && prefix.is_none() && dict_content == 0 {
+            self.ctx.reset_single_segment(window_size)?;
+        } else {
+            self.ctx.reset(window_size + dict_content)?;
+        }
+        if let Some(prefix) = prefix {
+            let start = prefix.len().saturating_sub(window_size);
+            self.ctx.window_buf.push_buf(&prefix[start..])?;
+        } else if let Some(dict) = &self.ctx.dictionary {
+            self.ctx.window_buf.push_buf(&dict.content)?;
+        }
+        Ok(Some(frame))
+    }
+
+    /// Decodes the frame's next block, flushing its output once at least
+    /// `flush_chunk` bytes are pending, and returns whether it was the last.
+    pub(crate) fn decode_block(
+        &mut self,
+        on_output: &mut impl FnMut(&[u8]) -> Result<(), Error>,
+        flushed: &mut u64,
+        flush_chunk: usize,
+        on_seq: &mut impl FnMut(&Sequence),
+    ) -> Result<bool, Error> {
+        let _frame = self.frame_span.clone().entered();
+
+        // The next block may shift the window, dropping output that hasn't
+        // been flushed yet.
+        if self.ctx.window_buf.will_shift(MAX_BLOCK_SIZE as usize) {
+            self.flush(on_output, flushed)?;
+        }
+
+        let last = self.ctx.block(on_seq).map_err(Error::mid_frame)?;
+        self.blocks += 1;
+        let pending = self.ctx.window_buf.position() - *flushed;
+        if pending >= flush_chunk as u64 || last {
+            self.flush(on_output, flushed)?;
+        }
+
+        Ok(last)
+    }
 
+    /// Checks the frame's checksum, if it has one, against its output.
+    pub(crate) fn end_frame(&mut self, frame: &frame::Header) -> Result<(), Error> {
+        let _frame =
+            std::mem::replace(&mut self.frame_span, tracing::Span::none()).entered();
         if frame.has_checksum() {
-            let expected_checksum = self.ctx.src.read_u32()?;
+            let expected_checksum = self
+                .ctx
+                .src
+                .read_u32()
+                .map_err(|err| Error::from(err).mid_frame())?;
+            if self.skip_checksum {
+                return Ok(());
+            }
+
             let computed_checksum = self.checksum.digest() as u32;
 
             if computed_checksum != expected_checksum {
                 return Err(Error::ChecksumMismatch);
             }
+            self.last_checksum = Some(computed_checksum);
         }
 
-        Ok(true)
+        Ok(())
+    }
+
+    /// Hands the output produced since `flushed` to `on_output`.
+    fn flush(
+        &mut self,
+        on_output: &mut impl FnMut(&[u8]) -> Result<(), Error>,
+        flushed: &mut u64,
+    ) -> Result<(), Error> {
+        let window = &self.ctx.window_buf;
+        let pending = (window.position() - *flushed) as usize;
+        if pending == 0 {
+            return Ok(());
+        }
+
+        if let Some(limit) = self.output_limit
+            && self.output + pending as u64 > limit
+        {
+            return Err(Error::OutputLimitExceeded(limit));
+        }
+
+        let data = &window.as_slice()[window.index() - pending..];
+        on_output(data)?;
+        // Frames without a checksum have nothing to verify it against.
+        if self.checksummed && !self.skip_checksum {
+            self.checksum.update(data);
+        }
+        if let Some(hasher) = &mut self.extra_hasher {
+            hasher.write(data);
+        }
+        self.output += pending as u64;
+
+        *flushed = window.position();
+        Ok(())
+    }
+
+    /// Reads the next frame's magic number, or `None` at the end of the input.
+    fn read_magic_num(&mut self) -> Result<Option<u32>, Error> {
+        let mut buf = [0u8; 4];
+        let mut read = 0;
+
+        while read < buf.len() {
+            match self.ctx.src.read(&mut buf[read..]) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::from(e)),
+            }
+        }
+
+        match read {
+            0 => Ok(None),
+            1..=3 if self.strict_trailing => Err(Error::TrailingGarbage(read)),
+            1..=3 => Ok(None),
+            _ => Ok(Some(u32::from_le_bytes(buf))),
+        }
+    }
+}
+
+impl<'b, 's> Decoder<'b, &'s [u8]> {
+    /// Decodes every frame like [Decoder::decode], but recovers from frames
+    /// that fail with a [corruption](Error::is_corruption) error.
+    ///
+    /// The input is scanned from just past the bad frame's start for the
+    /// next magic number, and decoding resumes there. Each skipped range of
+    /// input, relative to where the source stood when this was called, is
+    /// handed to `on_skip`. Output the bad frame produced before failing has
+    /// already been written. Scanning stops at the end of the input.
+    ///
+    /// If no magic number turns up within [Decoder::with_max_resync] bytes,
+    /// and the input goes on past them, the bad frame's error is returned
+    /// rather than skip that much input.
+    pub fn decode_lossy(
+        &mut self,
+        mut writer: impl std::io::Write,
+        mut on_skip: impl FnMut(Range<u64>),
+    ) -> Result<(), Error> {
+        let flush_chunk = self.flush_chunk;
+        let mut write = |data: &[u8]| writer.write_all(data).map_err(Error::from);
+
+        let input: &'s [u8] = self.ctx.src.get_ref().get_ref();
+        loop {
+            let start = input.len() - self.ctx.src.get_ref().get_ref().len();
+
+            match self.decode_frame(&mut write, flush_chunk, &mut ignore_sequence) {
+                Ok(true) => {}
+                Ok(false) => return Ok(()),
+                Err(err) if err.is_corruption() => {
+                    let from = start + 1;
+                    let next = match input[from..]
+                        .windows(4)
+                        .take(self.max_resync)
+                        .position(|w| w == MAGIC_NUM.to_le_bytes())
+                    {
+                        Some(i) => from + i,
+                        None if input.len() - from <= self.max_resync => input.len(),
+                        None => return Err(err),
+                    };
+
+                    tracing::debug!("skipping {start}..{next} after {err}");
+                    on_skip(start as u64..next as u64);
+                    self.ctx.src =
+                        TakeExact::new(CountingReader::new(&input[next..]), u64::MAX);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Where a [Decoder] keeps its window.
+#[derive(Debug)]
+enum WindowConfig<'b> {
+    /// An owned buffer, sized from each frame's header.
+    Auto,
+    /// A caller-provided buffer, along with the window size it holds.
+    Buffer(&'b mut [u8], usize),
+    /// A window in a caller-provided store.
+    Store(Window<'b>),
+}
+
+/// Configures a [Decoder] step by step.
+///
+/// Only the source is required: the window defaults to an owned buffer sized
+/// from each frame's header, and every other option to the same default as
+/// the matching `Decoder::with_*` method.
+#[derive(Debug)]
+pub struct DecoderBuilder<'b, R = ()> {
+    src: R,
+    window: WindowConfig<'b>,
+    strict: bool,
+    strict_verify: bool,
+    dictionary: Option<Dictionary>,
+    output_limit: Option<u64>,
+    flush_chunk: usize,
+    max_fse_accuracy_log: u8,
+    max_sequences: usize,
+    skip_checksum: bool,
+    max_resync: usize,
+}
+
+impl Default for DecoderBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DecoderBuilder<'_> {
+    pub fn new() -> Self {
+        Self {
+            src: (),
+            window: WindowConfig::Auto,
+            strict: false,
+            strict_verify: false,
+            dictionary: None,
+            output_limit: None,
+            flush_chunk: DEFAULT_FLUSH_CHUNK,
+            max_fse_accuracy_log: u8::MAX,
+            max_sequences: MAX_BLOCK_SIZE as usize,
+            skip_checksum: false,
+            max_resync: DEFAULT_MAX_RESYNC,
+        }
+    }
+}
+
+impl<'b, R> DecoderBuilder<'b, R> {
+    /// The compressed input.
+    pub fn source<S: rzstd_io::Reader>(self, src: S) -> DecoderBuilder<'b, S> {
+        DecoderBuilder {
+            src,
+            window: self.window,
+            strict: self.strict,
+            strict_verify: self.strict_verify,
+            dictionary: self.dictionary,
+            output_limit: self.output_limit,
+            flush_chunk: self.flush_chunk,
+            max_fse_accuracy_log: self.max_fse_accuracy_log,
+            max_sequences: self.max_sequences,
+            skip_checksum: self.skip_checksum,
+            max_resync: self.max_resync,
+        }
+    }
+
+    /// Decodes into `buf`, which must hold `window_size` plus
+    /// [MAX_BLOCK_SIZE] bytes for every frame, or just the content size of a
+    /// single-segment frame. See [Decoder::new].
+    pub fn window_buffer(mut self, buf: &'b mut [u8], window_size: usize) -> Self {
+        self.window = WindowConfig::Buffer(buf, window_size);
+        self
+    }
+
+    /// Decodes into `store`, which, if growable, is enlarged as each frame's
+    /// window needs, and otherwise must be as large as
+    /// [DecoderBuilder::window_buffer] requires, from `window_size` on.
+    ///
+    /// A `FileStore`, behind the `file-store` feature, keeps even 128 MiB
+    /// windows out of RAM.
+    pub fn window_store(
+        mut self,
+        store: impl WindowStore + 'static,
+        window_size: usize,
+    ) -> Self {
+        self.window = WindowConfig::Store(Window::with_store(store, window_size));
+        self
+    }
+
+    /// Decodes into an owned buffer sized from each frame's header. See
+    /// [Decoder::new_auto].
+    pub fn auto_window(mut self) -> Self {
+        self.window = WindowConfig::Auto;
+        self
+    }
+
+    /// Enables both [Decoder::with_strict_trailing] and
+    /// [Decoder::with_strict_unused_bit].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// See [Decoder::with_strict_verify].
+    pub fn strict_verify(mut self, strict: bool) -> Self {
+        self.strict_verify = strict;
+        self
+    }
+
+    /// Decodes every frame with `dict`, parsed here, failing the same way as
+    /// [Decoder::set_dictionary].
+    pub fn dictionary(mut self, dict: &[u8]) -> Result<Self, Error> {
+        self.dictionary = Some(Dictionary::read(dict)?);
+        Ok(self)
+    }
+
+    /// Fails with [Error::OutputLimitExceeded] rather than produce more than
+    /// `bytes` of output in total. Nothing past the limit reaches the output.
+    pub fn output_limit(mut self, bytes: u64) -> Self {
+        self.output_limit = Some(bytes);
+        self
+    }
+
+    /// See [Decoder::with_flush_chunk].
+    pub fn flush_chunk(mut self, bytes: NonZeroUsize) -> Self {
+        self.flush_chunk = bytes.get();
+        self
+    }
+
+    /// See [Decoder::with_max_fse_accuracy_log].
+    pub fn max_fse_accuracy_log(mut self, log: u8) -> Self {
+        self.max_fse_accuracy_log = log;
+        self
+    }
+
+    /// See [Decoder::with_max_sequences].
+    pub fn max_sequences(mut self, n: usize) -> Self {
+        self.max_sequences = n;
+        self
+    }
+
+    /// See [Decoder::with_skip_checksum].
+    pub fn skip_checksum(mut self, skip: bool) -> Self {
+        self.skip_checksum = skip;
+        self
+    }
+
+    /// See [Decoder::with_max_resync].
+    pub fn max_resync(mut self, bytes: usize) -> Self {
+        self.max_resync = bytes;
+        self
+    }
+}
+
+impl<'b, R: rzstd_io::Reader> DecoderBuilder<'b, R> {
+    pub fn build(self) -> Decoder<'b, R> {
+        let src = CountingReader::new(self.src);
+        let ctx = match self.window {
+            WindowConfig::Auto => {
+                Context::with_window(src, Window::growable(MIN_WINDOW_SIZE as usize))
+            }
+            WindowConfig::Buffer(buf, window_size) => Context::new(src, buf, window_size),
+            WindowConfig::Store(window) => Context::with_window(src, window),
+        };
+
+        let mut decoder = Decoder::with_context(ctx)
+            .with_strict_trailing(self.strict)
+            .with_strict_unused_bit(self.strict)
+            .with_strict_verify(self.strict_verify)
+            .with_max_fse_accuracy_log(self.max_fse_accuracy_log)
+            .with_max_sequences(self.max_sequences)
+            .with_skip_checksum(self.skip_checksum)
+            .with_max_resync(self.max_resync);
+        decoder.ctx.dictionary = self.dictionary;
+        decoder.flush_chunk = self.flush_chunk;
+        decoder.output_limit = self.output_limit;
+        decoder
+    }
+}
+
+fn ignore_sequence(_: &Sequence) {}
+
+/// Decodes every frame in `src` into a new vector, sizing the window from
+/// each frame's header.
+pub fn decompress(src: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    Decoder::new_auto(src).decode(&mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_BLOCK_SIZE;
+
+    const WINDOW_SIZE: usize = 8 << 20;
+
+    fn window_buf() -> Vec<u8> {
+        vec![0; WINDOW_SIZE + MAX_BLOCK_SIZE as usize]
+    }
+
+    #[test]
+    fn test_decode_one_frame() -> Result<(), Error> {
+        let first = b"the first frame, the first frame, the first frame".repeat(20);
+        let second = b"and then a second one".repeat(50);
+
+        let first_zst = zstd::encode_all(&first[..], 3)?;
+        let second_zst = zstd::encode_all(&second[..], 3)?;
+        let src = [first_zst.as_slice(), second_zst.as_slice()].concat();
+
+        let mut buf = window_buf();
+        let mut decoder = Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE);
+
+        let mut out = Vec::new();
+        let consumed = decoder.decode_one_frame(&mut out)?;
+        assert_eq!(consumed, Some(first_zst.len() as u64));
+        assert_eq!(out, first);
+
+        let mut out = Vec::new();
+        let consumed = decoder.decode_one_frame(&mut out)?;
+        assert_eq!(consumed, Some(second_zst.len() as u64));
+        assert_eq!(out, second);
+
+        assert_eq!(decoder.decode_one_frame(std::io::sink())?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_prefix() -> Result<(), Error> {
+        let content = (0..400_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 29) as u8)
+            .collect::<Vec<_>>();
+        let src = zstd::encode_all(&content[..], 3)?;
+
+        let mut decoder = Decoder::new_auto(&src[..]);
+        let mut out = Vec::new();
+        assert_eq!(decoder.decode_prefix(&mut out, 100)?, 100);
+        assert_eq!(out, content[..100]);
+        // Only part of the first block was decoded.
+        assert!(decoder.frame_position() < MAX_BLOCK_SIZE as u64);
+
+        let mut out = Vec::new();
+        let len = Decoder::new_auto(&src[..]).decode_prefix(&mut out, u64::MAX)?;
+        assert_eq!(len, content.len() as u64);
+        assert_eq!(out, content);
+
+        assert_eq!(Decoder::new_auto(&src[..]).decode_prefix(Vec::new(), 0)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_prefix_checksummed_block() -> Result<(), Error> {
+        // A single block, whose output is cut short before the checksum.
+        let content = (0..5000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 29) as u8)
+            .collect::<Vec<_>>();
+        let src = {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.include_checksum(true)?;
+            std::io::Write::write_all(&mut encoder, &content)?;
+            encoder.finish()?
+        };
+
+        let mut decoder = Decoder::new_auto(&src[..]);
+        let mut out = Vec::new();
+        assert_eq!(decoder.decode_prefix(&mut out, 100)?, 100);
+        assert_eq!(out, content[..100]);
+
+        let mut out = Vec::new();
+        let len = Decoder::new_auto(&src[..]).decode_prefix(&mut out, u64::MAX)?;
+        assert_eq!(len, content.len() as u64);
+
+        // The sequences past the limit are left in the bitstream.
+        let decoded_sequences = |cap| -> Result<usize, Error> {
+            let mut decoder = Decoder::new_auto(&src[..]);
+            decoder.begin_frame()?;
+            decoder.ctx.output_cap = cap;
+            decoder.ctx.block(&mut |_| {})?;
+            Ok(decoder.ctx.sequences_idx)
+        };
+        assert!(decoded_sequences(100)? < decoded_sequences(u64::MAX)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_with_stats() -> Result<(), Error> {
+        let contents = [
+            b"highly repetitive ".repeat(5000),
+            (0..70_000u32)
+                .map(|i| (i.wrapping_mul(2_654_435_761) >> 29) as u8)
+                .collect(),
+            Vec::new(),
+        ];
+        let frames = contents
+            .iter()
+            .map(|content| zstd::encode_all(&content[..], 3))
+            .collect::<Result<Vec<_>, _>>()?;
+        let src = frames.concat();
+
+        let mut stats = Vec::new();
+        let mut out = Vec::new();
+        Decoder::new_auto(&src[..])
+            .decode_with_stats(&mut out, |frame| stats.push(frame))?;
+        assert_eq!(out, contents.concat());
+
+        let expected = frames
+            .iter()
+            .zip(&contents)
+            .map(|(frame, content)| FrameStats {
+                compressed: frame.len() as u64,
+                decompressed: content.len() as u64,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(stats, expected);
+        assert_eq!(
+            stats[0].ratio(),
+            contents[0].len() as f64 / frames[0].len() as f64
+        );
+        assert!(stats[0].ratio() > stats[1].ratio());
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct CountingWriter {
+        out: Vec<u8>,
+        writes: usize,
+    }
+
+    impl std::io::Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.writes += 1;
+            self.out.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A frame with a 1 KiB window, made of `n` raw blocks of 1000 bytes.
+    fn raw_blocks_frame(n: usize) -> (Vec<u8>, Vec<u8>) {
+        let mut src = MAGIC_NUM.to_le_bytes().to_vec();
+        src.extend([0x00, 0x00]);
+
+        let mut content = Vec::new();
+        for i in 0..n {
+            let last = (i == n - 1) as u32;
+            src.extend(((1000 << 3) | last).to_le_bytes()[..3].iter());
+            src.extend([i as u8; 1000]);
+            content.extend([i as u8; 1000]);
+        }
+
+        (src, content)
+    }
+
+    #[test]
+    fn test_flush_chunk() -> Result<(), Error> {
+        let (src, content) = raw_blocks_frame(10);
+
+        let mut buf = window_buf();
+        let mut writer = CountingWriter::default();
+        Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE).decode(&mut writer)?;
+
+        assert_eq!(writer.out, content);
+        assert_eq!(writer.writes, 1);
+
+        let mut writer = CountingWriter::default();
+        Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE)
+            .with_flush_chunk(NonZeroUsize::new(1000).unwrap())
+            .decode(&mut writer)?;
+
+        assert_eq!(writer.out, content);
+        assert_eq!(writer.writes, 10);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_before_shift() -> Result<(), Error> {
+        let (src, content) = raw_blocks_frame(300);
+
+        // Tight enough that the window shifts every block once full.
+        let mut buf = vec![0; 1024 + MAX_BLOCK_SIZE as usize];
+        let mut writer = CountingWriter::default();
+        Decoder::new(src.as_slice(), &mut buf, 1024).decode(&mut writer)?;
+
+        assert_eq!(writer.out, content);
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_segment_tight_buffer() -> Result<(), Error> {
+        let content = (0..50_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 29) as u8)
+            .collect::<Vec<_>>();
+        let src = zstd::bulk::compress(&content, 3)?;
+        assert!(frame::Header::read(&mut &src[4..])?.is_single_segment());
+
+        // Just the content, without room for an extra block.
+        let mut buf = vec![0; content.len()];
+        let mut out = Vec::new();
+        Decoder::new(&src[..], &mut buf, content.len()).decode(&mut out)?;
+        assert_eq!(out, content);
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_segment_overproduces() -> Result<(), Error> {
+        // A single-segment frame declaring 1000 bytes of content, then two
+        // raw blocks of 600 bytes each.
+        let mut src = MAGIC_NUM.to_le_bytes().to_vec();
+        frame::Header::builder()
+            .content_size(1000)
+            .build()?
+            .write(&mut src)?;
+        src.extend(&((600 << 3) as u32).to_le_bytes()[..3]);
+        src.extend([0xaa; 600]);
+        src.extend(&(((600 << 3) | 1) as u32).to_le_bytes()[..3]);
+        src.extend([0xbb; 600]);
+
+        let header = frame::Header::read(&mut &src[4..])?;
+        assert!(header.is_single_segment());
+        assert_eq!(header.content_size(), Some(1000));
+
+        // The window has room for both blocks, but the frame said 1000 bytes.
+        let mut buf = window_buf();
+        let res = Decoder::new(&src[..], &mut buf, WINDOW_SIZE).decode(&mut Vec::new());
+        assert!(matches!(res, Err(Error::Corruption)));
+        assert!(matches!(decompress(&src), Err(Error::Corruption)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_streaming() -> Result<(), Error> {
+        let content = (0..4u32 << 20)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 29) as u8)
+            .collect::<Vec<_>>();
+        let src = zstd::encode_all(&content[..], 1)?;
+
+        let mut buf = window_buf();
+        let mut decoder = Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE);
+
+        let mut produced = 0;
+        let mut out = Vec::new();
+        decoder.decode_streaming(|data| {
+            assert!(data.len() <= MAX_BLOCK_SIZE as usize);
+            produced += data.len();
+            out.extend_from_slice(data);
+            Ok(())
+        })?;
+
+        assert_eq!(produced, content.len());
+        assert_eq!(out, content);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_paced() -> Result<(), Error> {
+        let (src, expected) = raw_blocks_frame(5);
+        let src = [src.as_slice(), src.as_slice()].concat();
+        let expected = [expected.as_slice(), &expected].concat();
+
+        let mut pieces = Vec::new();
+        let size = NonZeroUsize::new(777).unwrap();
+        Decoder::new_auto(&src[..]).decode_paced(size, |piece| {
+            pieces.push(piece.to_vec());
+            Ok(())
+        })?;
+
+        let (last, full) = pieces.split_last().unwrap();
+        assert!(full.iter().all(|piece| piece.len() == 777));
+        assert_eq!(last.len(), expected.len() % 777);
+        assert_eq!(pieces.concat(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trailing_garbage() -> Result<(), Error> {
+        let content = b"followed by a few stray bytes".repeat(10);
+        let frame = zstd::encode_all(&content[..], 3)?;
+
+        for n in 1..=3 {
+            let src = [frame.as_slice(), &[0xAA; 3][..n]].concat();
+            let mut buf = window_buf();
+
+            let mut out = Vec::new();
+            Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE).decode(&mut out)?;
+            assert_eq!(out, content);
+
+            let mut decoder = Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE)
+                .with_strict_trailing(true);
+            assert!(matches!(
+                decoder.decode(std::io::sink()),
+                Err(Error::TrailingGarbage(trailing)) if trailing == n
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_frame() -> Result<(), Error> {
+        let (src, _) = raw_blocks_frame(3);
+        let checksummed = {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.include_checksum(true)?;
+            std::io::Write::write_all(&mut encoder, b"cut short")?;
+            encoder.finish()?
+        };
+
+        // In the frame header, a block header, a block, and the checksum.
+        let cuts = [
+            &src[..5],
+            &src[..7],
+            &src[..1500],
+            &checksummed[..checksummed.len() - 2],
+        ];
+        for cut in cuts {
+            let res = Decoder::new_auto(cut).decode(std::io::sink());
+            assert!(matches!(res, Err(Error::TruncatedFrame)), "{res:?}");
+            assert!(res.unwrap_err().is_corruption());
+        }
+
+        // Running out of input between frames isn't a truncation.
+        Decoder::new_auto(&src[..]).decode(std::io::sink())?;
+        Decoder::new_auto(&[][..]).decode(std::io::sink())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompress() -> Result<(), Error> {
+        let content = b"sized from the frame header".repeat(1000);
+        let src = [
+            zstd::encode_all(&content[..], 3)?,
+            zstd::encode_all(&content[..100], 19)?,
+        ]
+        .concat();
+
+        assert_eq!(decompress(&src)?, [&content[..], &content[..100]].concat());
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksum_per_frame() -> Result<(), Error> {
+        let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+        encoder.include_checksum(true)?;
+        std::io::Write::write_all(&mut encoder, b"checksummed")?;
+        let frame = encoder.finish()?;
+
+        let src = [frame.as_slice(), frame.as_slice()].concat();
+        assert_eq!(decompress(&src)?, b"checksummedchecksummed");
+        Ok(())
+    }
+
+    #[test]
+    fn test_dictionary_every_frame() -> Result<(), Error> {
+        let samples = (0..1000u32)
+            .map(|i| {
+                let (name, score) = (i * 7 % 13, i * 31 % 97);
+                format!("{{\"id\": {i}, \"name\": \"user{name}\", \"score\": {score}}}")
+                    .into_bytes()
+            })
+            .collect::<Vec<_>>();
+        let dict = zstd::dict::from_samples(&samples, 4096)?;
+
+        let mut compressor = zstd::bulk::Compressor::with_dictionary(3, &dict)?;
+        let frames = [&samples[10], &samples[500], &samples[999]]
+            .map(|sample| compressor.compress(sample))
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        let src = frames.concat();
+
+        let mut decoder = Decoder::new_auto(&src[..]);
+        decoder.set_dictionary(&dict)?;
+        let mut out = Vec::new();
+        decoder.decode(&mut out)?;
+        let expected = [&samples[10][..], &samples[500], &samples[999]].concat();
+        assert_eq!(out, expected);
+
+        // Once cleared, the frames are decoded against nothing.
+        let mut decoder = Decoder::new_auto(&frames[0][..]);
+        decoder.set_dictionary(&dict)?;
+        decoder.clear_dictionary();
+        assert!(decoder.decode(&mut Vec::new()).is_err());
+
+        let mut other = dict.clone();
+        other[4] ^= 1;
+        let mut decoder = Decoder::new_auto(&frames[0][..]);
+        decoder.set_dictionary(&other)?;
+        assert!(matches!(
+            decoder.decode(&mut Vec::new()),
+            Err(Error::WrongDictionary { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefix_chaining() -> Result<(), Error> {
+        let first =
+            b"the first frame's output, which the second one refers to. ".repeat(50);
+        let second = [&first[100..600], b"and something new"].concat();
+
+        let first_frame = zstd::encode_all(&first[..], 3)?;
+        let second_frame = {
+            let mut encoder = zstd::Encoder::with_ref_prefix(Vec::new(), 19, &first)?;
+            std::io::Write::write_all(&mut encoder, &second)?;
+            encoder.finish()?
+        };
+
+        let mut decoder = Decoder::new_auto(&first_frame[..]);
+        let mut out = Vec::new();
+        decoder.decode(&mut out)?;
+        assert_eq!(out, first);
+        let history = decoder.into_history();
+        assert_eq!(history, first);
+
+        // Without the history, the matches reach past the start of the frame.
+        assert!(decompress(&second_frame).is_err());
+
+        let mut decoder = Decoder::new_auto(&second_frame[..]);
+        decoder.set_prefix(&history);
+        let mut out = Vec::new();
+        decoder.decode(&mut out)?;
+        assert_eq!(out, second);
+        assert_eq!(decoder.window_tail(second.len()), second);
+        assert_eq!(
+            decoder.window_tail(usize::MAX),
+            [&first[..], &second].concat()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mixed_checksum_flags() -> Result<(), Error> {
+        let frame = |content: &[u8], checksum: bool| -> Result<Vec<u8>, Error> {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.include_checksum(checksum)?;
+            std::io::Write::write_all(&mut encoder, content)?;
+            Ok(encoder.finish()?)
+        };
+        let frames = [
+            frame(&b"first".repeat(1000), true)?,
+            frame(b"second", false)?,
+            frame(b"third", true)?,
+        ];
+
+        let src = frames.concat();
+        let mut decoder = Decoder::new_auto(&src[..]);
+        let mut out = Vec::new();
+        for frame in &frames {
+            decoder.decode_one_frame(&mut out)?;
+            let stored = frame[frame.len() - 4..].try_into().unwrap();
+            // The checksum flag of the header descriptor.
+            let expected = (frame[4] & 0x04 != 0).then(|| u32::from_le_bytes(stored));
+            assert_eq!(decoder.last_checksum(), expected);
+        }
+        assert_eq!(decoder.decode_one_frame(&mut out)?, None);
+        assert_eq!(
+            out,
+            [&b"first".repeat(1000)[..], b"second", b"third"].concat()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_checksum() -> Result<(), Error> {
+        let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+        encoder.include_checksum(true)?;
+        std::io::Write::write_all(&mut encoder, b"skip the checksum")?;
+        let mut frame = encoder.finish()?;
+
+        let mut decoder = Decoder::new_auto(&frame[..]);
+        decoder.decode(std::io::sink())?;
+        let checksum = decoder.last_checksum().expect("frame has a checksum");
+        assert_eq!(frame[frame.len() - 4..], checksum.to_le_bytes());
+
+        *frame.last_mut().unwrap() ^= 1;
+        let src = [frame.as_slice(), &frame].concat();
+        let mut decoder = DecoderBuilder::new()
+            .source(&src[..])
+            .skip_checksum(true)
+            .build();
+        let mut out = Vec::new();
+        decoder.decode(&mut out)?;
+        assert_eq!(out, b"skip the checksum".repeat(2));
+        assert_eq!(decoder.last_checksum(), None);
+
+        let (unchecked, _) = raw_blocks_frame(2);
+        let mut decoder = Decoder::new_auto(&unchecked[..]);
+        decoder.decode(std::io::sink())?;
+        assert_eq!(decoder.last_checksum(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify() -> Result<(), Error> {
+        let frame = |checksum: bool| -> Result<Vec<u8>, Error> {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.include_checksum(checksum)?;
+            std::io::Write::write_all(&mut encoder, b"verify me")?;
+            Ok(encoder.finish()?)
+        };
+        let verify = |src: &[u8], strict: bool| {
+            DecoderBuilder::new()
+                .source(src)
+                .strict_verify(strict)
+                .build()
+                .verify()
+        };
+
+        let valid = frame(true)?;
+        assert!(verify(&valid, false)?);
+        assert!(verify(
+            &[valid.as_slice(), valid.as_slice()].concat(),
+            true
+        )?);
+
+        let mut corrupted = valid.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(!verify(&corrupted, false)?);
+        assert!(!verify(
+            &[valid.as_slice(), corrupted.as_slice()].concat(),
+            false
+        )?);
+
+        let unchecked = frame(false)?;
+        assert!(verify(&unchecked, false)?);
+        assert!(!verify(&unchecked, true)?);
+        // Strict decoding alone doesn't make verification strict.
+        let mut decoder = DecoderBuilder::new()
+            .source(&unchecked[..])
+            .strict(true)
+            .build();
+        assert!(decoder.verify()?);
+
+        let truncated = &valid[..valid.len() - 1];
+        assert!(verify(truncated, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_to_sink() -> Result<(), Error> {
+        let (src, expected) = raw_blocks_frame(3);
+        let src = [src.as_slice(), src.as_slice()].concat();
+
+        let total = Decoder::new_auto(&src[..]).decode_to_sink()?;
+        assert_eq!(total, 2 * expected.len() as u64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunks() -> Result<(), Error> {
+        let (src, expected) = raw_blocks_frame(5);
+        let src = [src.as_slice(), src.as_slice()].concat();
+
+        let chunks = Decoder::new_auto(&src[..])
+            .with_flush_chunk(NonZeroUsize::new(1500).unwrap())
+            .chunks()
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 2000));
+        assert_eq!(chunks.concat(), [expected.as_slice(), &expected].concat());
+
+        let mut chunks = Decoder::new_auto(&src[..src.len() - 1]).chunks();
+        let ok = chunks.by_ref().take_while(Result::is_ok).count();
+        assert_eq!(ok, 7);
+        assert!(chunks.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tracing_spans() -> Result<(), Error> {
+        use std::sync::{Arc, Mutex};
+
+        use tracing_subscriber::{
+            Layer, layer::Context as LayerContext, prelude::*, registry::LookupSpan,
+        };
+
+        /// Records every span as its path from the root, followed by its
+        /// fields.
+        #[derive(Clone, Default)]
+        struct Spans(Arc<Mutex<Vec<String>>>);
+
+        impl<S> Layer<S> for Spans
+        where
+            S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+        {
+            fn on_new_span(
+                &self,
+                attrs: &tracing::span::Attributes<'_>,
+                _: &tracing::span::Id,
+                ctx: LayerContext<'_, S>,
+            ) {
+                struct Fields(String);
+                impl tracing::field::Visit for Fields {
+                    fn record_debug(
+                        &mut self,
+                        field: &tracing::field::Field,
+                        value: &dyn std::fmt::Debug,
+                    ) {
+                        self.0 += &format!("{}={:?} ", field.name(), value);
+                    }
+                }
+
+                let mut fields = Fields(String::new());
+                attrs.record(&mut fields);
+                let parent = ctx
+                    .lookup_current()
+                    .map(|span| format!("{}/", span.name()))
+                    .unwrap_or_default();
+                self.0.lock().unwrap().push(format!(
+                    "{parent}{} {}",
+                    attrs.metadata().name(),
+                    fields.0.trim_end()
+                ));
+            }
+        }
+
+        let (frame, _) = raw_blocks_frame(2);
+        let src = [frame.as_slice(), &frame].concat();
+
+        let spans = Spans::default();
+        let subscriber = tracing_subscriber::registry().with(spans.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            Decoder::new_auto(&src[..]).decode(std::io::sink())
+        })?;
+
+        let frame = |index| format!("frame index={index} window_size=1024");
+        let block = || "frame/block ty=Raw size=1000".to_string();
+        assert_eq!(
+            *spans.0.lock().unwrap(),
+            [frame(0), block(), block(), frame(1), block(), block()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_lossy() -> Result<(), Error> {
+        let (first, first_content) = raw_blocks_frame(3);
+        let (second, second_content) = raw_blocks_frame(2);
+
+        // Cut the first frame short in the middle of its second block.
+        let truncated = &first[..first.len() - 1500];
+        let src = [truncated, &second, &[0xff; 5]].concat();
+
+        let mut buf = window_buf();
+        assert!(
+            Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE)
+                .decode(std::io::sink())
+                .is_err()
+        );
+
+        let mut out = Vec::new();
+        let mut skipped = Vec::new();
+        Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE)
+            .decode_lossy(&mut out, |range| skipped.push(range))?;
+
+        let n = truncated.len() as u64;
+        assert_eq!(skipped, [0..n, n + second.len() as u64..src.len() as u64]);
+        assert!(out.starts_with(&first_content[..1000]));
+        assert!(out.ends_with(&second_content));
+
+        // The second frame lies further than the bad one allows resyncing.
+        let res = Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE)
+            .with_max_resync(100)
+            .decode_lossy(std::io::sink(), |_| {});
+        assert!(res.is_err_and(|err| err.is_corruption()));
+
+        // But not further than the end of the input, past the bad trailer.
+        let mut skipped = Vec::new();
+        Decoder::new(&src[truncated.len()..], &mut buf, WINDOW_SIZE)
+            .with_max_resync(100)
+            .decode_lossy(std::io::sink(), |range| skipped.push(range))?;
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0], second.len() as u64..src.len() as u64 - n);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_each_frame() -> Result<(), Error> {
+        let (first, first_content) = raw_blocks_frame(3);
+        let (second, second_content) = raw_blocks_frame(1);
+        let src = [first.as_slice(), second.as_slice()].concat();
+
+        let mut frames = Vec::new();
+        Decoder::new_auto(src.as_slice()).decode_each_frame(|idx, data| {
+            frames.push((idx, data.to_vec()));
+            Ok(())
+        })?;
+        assert_eq!(frames, [(0, first_content), (1, second_content)]);
+
+        // Both frames fit the limit on their own, but not together.
+        let mut decoder = DecoderBuilder::new()
+            .source(src.as_slice())
+            .output_limit(3000)
+            .build();
+        let mut n_frames = 0;
+        decoder.decode_each_frame(|_, _| {
+            n_frames += 1;
+            Ok(())
+        })?;
+        assert_eq!(n_frames, 2);
+
+        Ok(())
+    }
+
+    /// A single-segment frame holding one compressed block: the literals
+    /// The first state of a predefined table decoding to `symbol`.
+    fn state_of<const N: usize>(dist: crate::DefaultDistribution, symbol: u8) -> u64 {
+        let table = dist
+            .build_table::<N>()
+            .expect("predefined distributions are valid");
+
+        (0..)
+            .find(|&state| table.entry(state).expect("symbol is present").0 == symbol)
+            .expect("symbol is present") as u64
+    }
+
+    /// A last, compressed block of raw `literals` (under 32 bytes), then a
+    /// single sequence coded with the predefined tables: literal length code
+    /// `ll`, offset code `of` with its extra bits, and match length code `ml`.
+    /// `ll` and `ml` must be codes without extra bits.
+    fn single_sequence_block(
+        literals: &[u8],
+        ll: u8,
+        of: u8,
+        of_extra: u64,
+        ml: u8,
+    ) -> Vec<u8> {
+        use crate::{LL_DIST, ML_DIST, OF_DIST};
+
+        let mut w = rzstd_io::ReverseBitWriter::new();
+        w.write(of_extra, of);
+        w.write(state_of::<{ ML_DIST.table_size() }>(ML_DIST, ml), 6);
+        w.write(state_of::<{ OF_DIST.table_size() }>(OF_DIST, of), 5);
+        w.write(state_of::<{ LL_DIST.table_size() }>(LL_DIST, ll), 6);
+        let sequences = [&[1, 0][..], &w.finish()].concat();
+
+        let block = [&[(literals.len() as u8) << 3][..], literals, &sequences].concat();
+        let block_header = 1 | (2 << 1) | ((block.len() as u32) << 3);
+        [&block_header.to_le_bytes()[..3], &block].concat()
+    }
+
+    /// `abcd` followed by a 4-byte match at repeat offset 1, coded with the
+    /// predefined tables.
+    fn repeat_offset_frame() -> Vec<u8> {
+        // Literal length 4, offset code 0 (repeat offset 1), match length 4.
+        let block = single_sequence_block(b"abcd", 4, 0, 0, 1);
+
+        let mut src = MAGIC_NUM.to_le_bytes().to_vec();
+        src.extend([0x20, 8]);
+        src.extend(block);
+        src
+    }
+
+    #[test]
+    fn test_min_window_offsets() -> Result<(), Error> {
+        // Two raw blocks fill a 1 KiB window past its size, shifting it, then
+        // a 4-byte match reaches back `offset` bytes.
+        let frame = |offset: u64| {
+            let (mut src, content) = raw_blocks_frame(2);
+            let header = src.len() - 1003;
+            src[header] &= !1;
+
+            // Offset values past 3 are offsets plus 3, their code the highest
+            // bit set.
+            let value = offset + 3;
+            let code = value.ilog2() as u8;
+            src.extend(single_sequence_block(&[], 0, code, value - (1 << code), 1));
+            (src, content)
+        };
+
+        for offset in [4, 1000, MIN_WINDOW_SIZE] {
+            let (src, content) = frame(offset);
+            let out = decompress(&src)?;
+            let start = content.len() - offset as usize;
+            assert_eq!(out[..content.len()], content);
+            assert_eq!(out[content.len()..], content[start..][..4]);
+        }
+
+        let (src, _) = frame(MIN_WINDOW_SIZE + 1);
+        assert!(matches!(
+            decompress(&src),
+            Err(Error::CopiedSizeOutOfBounds)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_initial_offsets() -> Result<(), Error> {
+        let src = repeat_offset_frame();
+
+        let mut out = Vec::new();
+        Decoder::new_auto(src.as_slice()).decode(&mut out)?;
+        assert_eq!(out, b"abcddddd");
+
+        let mut decoder = Decoder::new_auto(src.as_slice());
+        decoder.set_initial_offsets([3, 1, 2])?;
+        let mut out = Vec::new();
+        decoder.decode(&mut out)?;
+        assert_eq!(out, b"abcdbcdb");
+
+        assert!(matches!(
+            decoder.set_initial_offsets([3, 0, 2]),
+            Err(Error::ZeroOffset)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_over_produces() {
+        // The block regenerates 8 bytes, but the frame's single segment, and
+        // so its window, only holds 6.
+        let mut src = repeat_offset_frame();
+        src[5] = 6;
+
+        let res = Decoder::new_auto(src.as_slice()).decode(&mut Vec::new());
+        assert!(matches!(res, Err(Error::Corruption)));
+    }
+
+    #[test]
+    fn test_decode_into_vec() -> Result<(), Error> {
+        let (large, large_content) = raw_blocks_frame(5);
+        let (small, small_content) = raw_blocks_frame(2);
+
+        let mut out = vec![0xff; 10];
+        Decoder::new_auto(large.as_slice()).decode_into_vec(&mut out)?;
+        assert_eq!(out, large_content);
+
+        let (ptr, capacity) = (out.as_ptr(), out.capacity());
+        for src in [&small, &large] {
+            Decoder::new_auto(src.as_slice()).decode_into_vec(&mut out)?;
+            assert_eq!((out.as_ptr(), out.capacity()), (ptr, capacity));
+        }
+        assert_eq!(out, large_content);
+
+        Decoder::new_auto(small.as_slice()).decode_into_vec(&mut out)?;
+        assert_eq!(out, small_content);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_with_progress() -> Result<(), Error> {
+        /// Takes at most 100 bytes per call.
+        #[derive(Default)]
+        struct Trickle(Vec<u8>);
+
+        impl std::io::Write for Trickle {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                let n = buf.len().min(100);
+                self.0.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let (src, content) = raw_blocks_frame(5);
+        let mut sink = Trickle::default();
+        let mut progress = Vec::new();
+        Decoder::new_auto(src.as_slice())
+            .decode_with_progress(&mut sink, |written| progress.push(written))?;
+
+        assert_eq!(sink.0, content);
+        assert_eq!(progress.len(), content.len() / 100);
+        assert!(progress.windows(2).all(|w| w[1] - w[0] == 100));
+        assert_eq!(progress.last(), Some(&(content.len() as u64)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_extra_hasher() -> Result<(), Error> {
+        use std::hash::{DefaultHasher, Hasher};
+
+        let (src, content) = raw_blocks_frame(5);
+        let src = [src.as_slice(), src.as_slice()].concat();
+
+        let mut decoder = Decoder::new_auto(src.as_slice())
+            .with_flush_chunk(NonZeroUsize::new(1500).unwrap())
+            .with_extra_hasher(DefaultHasher::new());
+        let mut out = Vec::new();
+        decoder.decode(&mut out)?;
+        assert_eq!(out, [content.as_slice(), &content].concat());
+
+        let mut expected = DefaultHasher::new();
+        expected.write(&out);
+        assert_eq!(decoder.extra_digest(), Some(expected.finish()));
+
+        assert_eq!(Decoder::new_auto(src.as_slice()).extra_digest(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_sequences() {
+        // One compressed block of empty raw literals, then a sequences header
+        // claiming the most sequences it can, over predefined tables, and a
+        // bitstream with no room for any of them.
+        let mut src = MAGIC_NUM.to_le_bytes().to_vec();
+        src.extend([0x00, 0x00]);
+        src.extend([(6 << 3) | (2 << 1) | 1, 0, 0]);
+        src.extend([0x00, 255, 0xFF, 0xFF, 0x00, 0x01]);
+
+        let res = Decoder::new_auto(src.as_slice())
+            .with_max_sequences(1000)
+            .decode(std::io::sink());
+        assert!(matches!(res, Err(Error::Corruption)), "{res:?}");
+
+        // Within the default bound, the stream is read and found too short.
+        let res = Decoder::new_auto(src.as_slice()).decode(std::io::sink());
+        assert!(!matches!(res, Ok(()) | Err(Error::Corruption)), "{res:?}");
+    }
+
+    #[test]
+    fn test_builder() -> Result<(), Error> {
+        let (src, content) = raw_blocks_frame(10);
+
+        let mut buf = window_buf();
+        let mut writer = CountingWriter::default();
+        DecoderBuilder::new()
+            .strict(true)
+            .flush_chunk(NonZeroUsize::new(2000).unwrap())
+            .max_fse_accuracy_log(6)
+            .source(src.as_slice())
+            .window_buffer(&mut buf, WINDOW_SIZE)
+            .build()
+            .decode(&mut writer)?;
+        assert_eq!(writer.out, content);
+        assert_eq!(writer.writes, 5);
+
+        let strict = [&src[..], &[0; 2]].concat();
+        let mut decoder = DecoderBuilder::new()
+            .source(strict.as_slice())
+            .strict(true)
+            .build();
+        assert!(matches!(
+            decoder.decode(std::io::sink()),
+            Err(Error::TrailingGarbage(2))
+        ));
+
+        let mut out = Vec::new();
+        let mut decoder = DecoderBuilder::new()
+            .source(src.as_slice())
+            .output_limit(content.len() as u64)
+            .build();
+        decoder.decode(&mut out)?;
+        assert_eq!(out, content);
+
+        let mut out = Vec::new();
+        let mut decoder = DecoderBuilder::new()
+            .source(src.as_slice())
+            .flush_chunk(NonZeroUsize::new(1000).unwrap())
+            .output_limit(content.len() as u64 - 1)
+            .build();
+        assert!(matches!(
+            decoder.decode(&mut out),
+            Err(Error::OutputLimitExceeded(limit)) if limit == content.len() as u64 - 1
+        ));
+        assert_eq!(out, content[..content.len() - 1000]);
+
+        let dict = b"dictionary content, dictionary content".repeat(4);
+        let frame = zstd::bulk::Compressor::with_dictionary(3, &dict)?
+            .compress(b"some dictionary content")?;
+        let mut out = Vec::new();
+        DecoderBuilder::new()
+            .source(frame.as_slice())
+            .dictionary(&dict)?
+            .build()
+            .decode(&mut out)?;
+        assert_eq!(out, b"some dictionary content");
+        assert!(
+            Decoder::new_auto(frame.as_slice())
+                .decode(Vec::new())
+                .is_err()
+        );
+
+        // Entropy tables announced by the magic number, but missing.
+        let magic = crate::dictionary::DICTIONARY_MAGIC_NUM;
+        let truncated = magic.to_le_bytes().repeat(2);
+        assert!(DecoderBuilder::new().dictionary(&truncated).is_err());
+
+        Ok(())
+    }
+
+    /// Counts the `read` calls reaching the underlying source.
+    #[derive(Debug)]
+    struct CountingReads<'a> {
+        src: &'a [u8],
+        reads: usize,
+    }
+
+    impl std::io::Read for CountingReads<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.reads += 1;
+            self.src.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_new_buffered() -> Result<(), Error> {
+        let content = b"many small frames".repeat(4);
+        let frame = zstd::encode_all(&content[..], 3)?;
+        let src = frame.repeat(100);
+
+        let mut out = Vec::new();
+        let mut decoder = Decoder::new_buffered(CountingReads {
+            src: &src,
+            reads: 0,
+        });
+        decoder.decode(&mut out)?;
+        assert_eq!(out, content.repeat(100));
+
+        // The whole input fits in the buffer: one read fills it, one more
+        // hits the end.
+        let reads = decoder.ctx.src.into_inner().into_inner().into_inner().reads;
+        assert_eq!(reads, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_window_grows_on_demand() -> Result<(), Error> {
+        // No content size and a window descriptor declaring 128 MiB.
+        let header = [0x28, 0xB5, 0x2F, 0xFD, 0x00, 17 << 3];
+        let raw_block = |len: u32, last: bool| (last as u32 | (len << 3)).to_le_bytes();
+
+        let content = (0..1000u32).map(|i| i as u8).collect::<Vec<_>>();
+        let src = [&header[..], &raw_block(1000, true)[..3], &content].concat();
+
+        let mut decoder = Decoder::new_auto(src.as_slice());
+        let mut out = Vec::new();
+        decoder.decode(&mut out)?;
+
+        assert_eq!(out, content);
+        assert_eq!(
+            decoder.ctx.window_buf.size(),
+            crate::MAX_WINDOW_SIZE as usize
+        );
+        assert!(decoder.ctx.window_buf.capacity() < 1 << 20);
+
+        // Growing past the initial allocation keeps earlier output intact.
+        let content = (0..600_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut src = header.to_vec();
+        for (i, block) in content.chunks(100_000).enumerate() {
+            src.extend_from_slice(&raw_block(block.len() as u32, i == 5)[..3]);
+            src.extend_from_slice(block);
+        }
+        let long_match = zstd::encode_all(&[&content[..], &content[..]].concat()[..], 3)?;
+
+        assert_eq!(decompress(&src)?, content);
+        assert_eq!(
+            decompress(&long_match)?,
+            [&content[..], &content[..]].concat()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_buffers_reused_across_frames() -> Result<(), Error> {
+        let content = (0..200_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 29) as u8)
+            .collect::<Vec<_>>();
+        let frame = zstd::encode_all(&content[..], 3)?;
+        let src = [frame.as_slice(), frame.as_slice()].concat();
+
+        let mut buf = window_buf();
+        let mut decoder = Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE);
+
+        let buffers = |ctx: &Context<'_, _>| {
+            [
+                (
+                    ctx.literals_buf.as_ptr() as usize,
+                    ctx.literals_buf.capacity(),
+                ),
+                (
+                    ctx.sequences_buf.as_ptr() as usize,
+                    ctx.sequences_buf.capacity(),
+                ),
+                (
+                    ctx.scratch_buf.as_ptr() as usize,
+                    ctx.scratch_buf.capacity(),
+                ),
+            ]
+        };
+        let before = buffers(&decoder.ctx);
+
+        for _ in 0..2 {
+            let mut out = Vec::new();
+            decoder.decode_one_frame(&mut out)?;
+            assert_eq!(out, content);
+            assert_eq!(buffers(&decoder.ctx), before);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_grows_between_frames() -> Result<(), Error> {
+        let small = b"a small first frame".repeat(10);
+        let half = (0..300_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 27) as u8)
+            .collect::<Vec<_>>();
+        let large = [&half[..], &half[..]].concat();
+
+        // With the size pledged up front, both frames are single-segment
+        // with a window as large as their content.
+        let compress = |content: &[u8]| -> std::io::Result<Vec<u8>> {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.set_pledged_src_size(Some(content.len() as u64))?;
+            encoder.include_contentsize(true)?;
+            std::io::Write::write_all(&mut encoder, content)?;
+            encoder.finish()
+        };
+        let src = [compress(&small)?, compress(&large)?].concat();
+        let mut buf = vec![0; small.len() + MAX_BLOCK_SIZE as usize];
+        let mut decoder = Decoder::new(src.as_slice(), &mut buf, small.len());
+        let mut out = Vec::new();
+        decoder.decode_one_frame(&mut out)?;
+        assert_eq!(out, small);
+        assert!(matches!(
+            decoder.decode_one_frame(&mut out),
+            Err(Error::WindowBufferTooSmall { needed, have })
+                if needed == large.len() as u64
+                    && have == small.len() + MAX_BLOCK_SIZE as usize
+        ));
+
+        assert_eq!(decompress(&src)?, [&small[..], &large[..]].concat());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_sequences() -> Result<(), Error> {
+        let content = b"abcdefgh".repeat(1000);
+        let src = zstd::encode_all(&content[..], 3)?;
+
+        let mut buf = window_buf();
+        let mut decoder = Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE);
+
+        let mut sequences = Vec::new();
+        decoder.decode_sequences(|seq| sequences.push(*seq))?;
+
+        // A single block, whose matches all reach back one period, however
+        // the offsets were coded.
+        assert_eq!(sequences.len(), decoder.ctx.sequences_idx);
+        assert!(sequences.iter().all(|seq| seq.offset == 8));
+
+        let covered = sequences
+            .iter()
+            .map(|seq| (seq.lit_len + seq.match_len) as usize)
+            .sum::<usize>();
+        assert!(covered <= content.len());
+        assert!(covered >= content.len() - 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unused_bit() -> Result<(), Error> {
+        let content = b"an otherwise valid frame".repeat(10);
+        let mut src = zstd::encode_all(&content[..], 3)?;
+        src[4] |= 0x10;
+
+        let mut buf = window_buf();
+        let mut out = Vec::new();
+        Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE).decode(&mut out)?;
+        assert_eq!(out, content);
+
+        let mut decoder = Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE)
+            .with_strict_unused_bit(true);
+        assert!(matches!(
+            decoder.decode(std::io::sink()),
+            Err(Error::UnusedBitSet)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "allocates several hundred MiB"]
+    fn test_max_window_long_match() -> Result<(), Error> {
+        // Incompressible data, then its start again, 110 MiB back: further
+        // than a 100 MiB window reaches.
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let mut content = (0..110 << 20)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect::<Vec<_>>();
+        content.extend_from_within(..1 << 20);
+
+        let src = {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 1)?;
+            encoder.include_contentsize(false)?;
+            encoder.window_log(27)?;
+            encoder.long_distance_matching(true)?;
+            std::io::Write::write_all(&mut encoder, &content)?;
+            encoder.finish()?
+        };
+        assert!(
+            src.len() < content.len() - (1 << 19),
+            "the match wasn't found"
+        );
+        let window_size = frame::Header::read(&mut &src[4..])?.window_size()?;
+        assert_eq!(window_size, crate::MAX_WINDOW_SIZE);
+
+        let mut out = Vec::new();
+        Decoder::new_auto(src.as_slice()).decode(&mut out)?;
+        assert!(out == content);
+        Ok(())
+    }
+
+    #[test]
+    fn test_huge_single_segment_frame() -> Result<(), Error> {
+        // A single-segment frame whose 8-byte content size is u64::MAX, so
+        // the window is as large as the content.
+        let mut src = MAGIC_NUM.to_le_bytes().to_vec();
+        src.push(0xE0);
+        src.extend(u64::MAX.to_le_bytes());
+        src.extend(1u32.to_le_bytes()[..3].iter());
+
+        let mut buf = window_buf();
+        let mut decoder = Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE);
+        assert!(matches!(
+            decoder.decode(std::io::sink()),
+            Err(Error::WindowBufferTooSmall {
+                needed: u64::MAX,
+                ..
+            })
+        ));
+
+        let mut out = Vec::new();
+        Decoder::new_auto(src.as_slice()).decode(&mut out)?;
+        assert!(out.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_larger_than_window() {
+        // Single-segment frame of 16 bytes, so a 16 byte window, followed by
+        // a raw block of 32 bytes.
+        let mut src = MAGIC_NUM.to_le_bytes().to_vec();
+        src.extend([0x20, 16]);
+        src.extend(((32 << 3) | 1u32).to_le_bytes()[..3].iter());
+        src.extend([0xAB; 32]);
+
+        let mut buf = window_buf();
+        let mut decoder = Decoder::new(src.as_slice(), &mut buf, WINDOW_SIZE);
+
+        assert!(matches!(
+            decoder.decode(std::io::sink()),
+            Err(Error::BlockSizeOutOfBounds(32))
+        ));
+    }
+
+    #[test]
+    fn test_literals_only_blocks() -> Result<(), Error> {
+        // Skewed but match-free bytes: the blocks hold Huffman-coded literals
+        // and no sequences at all.
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let src = (0..512 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                ((state & 0x7F) + ((state >> 8) & 0x7F)) as u8
+            })
+            .collect::<Vec<_>>();
+        let compressed = zstd::encode_all(&src[..], 1)?;
+
+        let mut buf = window_buf();
+        let mut decoder = Decoder::new(compressed.as_slice(), &mut buf, WINDOW_SIZE);
+
+        let mut out = Vec::new();
+        decoder.decode(&mut out)?;
+        assert_eq!(out, src);
+
+        Ok(())
     }
 }
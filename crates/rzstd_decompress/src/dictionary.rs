@@ -0,0 +1,99 @@
+use crate::{
+    LL_DIST, ML_DIST, OF_DIST,
+    context::{DEFAULT_OFFSETS, FSEContext},
+    errors::Error,
+    sequences_section::{Mode, update_table},
+};
+
+/// Magic number of dictionaries carrying entropy tables on top of their
+/// content.
+pub const DICTIONARY_MAGIC_NUM: u32 = 0xEC30_A437;
+
+/// What every frame decoded with a dictionary starts with: its entropy tables,
+/// repeat offsets and content.
+#[derive(Debug, Clone)]
+pub struct Dictionary {
+    /// Zero for raw content dictionaries, which frames can't ask for by ID.
+    pub id: u32,
+    pub huff: Option<rzstd_huff0::DecodingTable>,
+    pub fse: FSEContext,
+    pub offsets: [usize; 3],
+    pub content: Vec<u8>,
+}
+
+impl Dictionary {
+    /// Parses `src` as a zstd dictionary if it starts with
+    /// [DICTIONARY_MAGIC_NUM], and takes all of it as raw content otherwise.
+    pub fn read(src: &[u8]) -> Result<Self, Error> {
+        let word = |src: &[u8]| -> Result<u32, Error> {
+            let bytes = src.first_chunk().ok_or(Error::Corruption)?;
+            Ok(u32::from_le_bytes(*bytes))
+        };
+
+        if src.len() < 8 || word(src)? != DICTIONARY_MAGIC_NUM {
+            return Ok(Self {
+                id: 0,
+                huff: None,
+                fse: FSEContext {
+                    ll: None,
+                    ml: None,
+                    of: None,
+                },
+                offsets: DEFAULT_OFFSETS,
+                content: src.to_vec(),
+            });
+        }
+
+        let id = word(&src[4..])?;
+        let mut r = &src[8..];
+
+        let (huff, read) = rzstd_huff0::DecodingTable::read(r)?;
+        r = &r[read..];
+
+        // Unlike in blocks, the tables come offsets first, and may use the
+        // largest accuracy log each symbol type allows.
+        let mut fse = FSEContext {
+            ll: None,
+            ml: None,
+            of: None,
+        };
+        let max_log = u8::MAX;
+        r = &r[update_table(Mode::FSECompressed, OF_DIST, r, &mut fse.of, max_log)?..];
+        r = &r[update_table(Mode::FSECompressed, ML_DIST, r, &mut fse.ml, max_log)?..];
+        r = &r[update_table(Mode::FSECompressed, LL_DIST, r, &mut fse.ll, max_log)?..];
+
+        let mut offsets = [0; 3];
+        for offset in &mut offsets {
+            *offset = word(r)? as usize;
+            r = &r[4..];
+        }
+
+        // Every repeat offset must point into the content.
+        if offsets.contains(&0) || offsets.iter().any(|&offset| offset > r.len()) {
+            return Err(Error::Corruption);
+        }
+
+        Ok(Self {
+            id,
+            huff: Some(huff),
+            fse,
+            offsets,
+            content: r.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_content() -> Result<(), Error> {
+        let dict = Dictionary::read(b"raw content")?;
+        assert_eq!(dict.id, 0);
+        assert!(dict.huff.is_none());
+        assert_eq!(dict.offsets, DEFAULT_OFFSETS);
+        assert_eq!(dict.content, b"raw content");
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+// The derived `Diagnostic` impl destructures struct-like variants, which trips
+// a false `unused_assignments` positive on rustc 1.92.
+#![allow(unused_assignments)]
+
 use crate::MAGIC_NUM;
 
 #[derive(Debug, thiserror::Error, miette::Diagnostic)]
@@ -19,6 +23,27 @@ pub enum Error {
     )]
     WindowSizeOutOfBounds(u64),
 
+    #[error("Window buffer of {have} bytes is too small, {needed} are needed")]
+    #[diagnostic(
+        code(rzstd::decompress::window_buffer_too_small),
+        help("A frame declares a larger window than the provided buffer can hold.")
+    )]
+    WindowBufferTooSmall { needed: u64, have: usize },
+
+    #[error("Output exceeds the limit of {0} bytes")]
+    #[diagnostic(
+        code(rzstd::decompress::output_limit_exceeded),
+        help("The input decodes to more data than the decoder was configured to produce.")
+    )]
+    OutputLimitExceeded(u64),
+
+    #[error("Frame index {0} is out of range")]
+    #[diagnostic(
+        code(rzstd::decompress::frame_index_out_of_range),
+        help("The input holds fewer frames than the requested index implies.")
+    )]
+    FrameIndexOutOfRange(usize),
+
     #[error("Reserved bit is set")]
     #[diagnostic(
         code(rzstd::decompress::reserved_bit_set),
@@ -26,12 +51,19 @@ pub enum Error {
     )]
     ReservedBitSet,
 
-    #[error("Reserved block type")]
+    #[error("Unused bit is set")]
+    #[diagnostic(
+        code(rzstd::decompress::unused_bit_set),
+        help("The unused bit of the frame header descriptor is set, which strict decoding rejects.")
+    )]
+    UnusedBitSet,
+
+    #[error("Reserved block type in block header {0:#08x}")]
     #[diagnostic(
         code(rzstd::decompress::reserved_block),
         help("Encountered a block type that is reserved.")
     )]
-    ReservedBlock,
+    ReservedBlock(u32),
 
     #[error("Invalid block type {0}")]
     #[diagnostic(
@@ -61,6 +93,15 @@ pub enum Error {
     )]
     MissingHuffTable,
 
+    #[error("Treeless literals without a previous Huffman table")]
+    #[diagnostic(
+        code(rzstd::decompress::treeless_without_table),
+        help(
+            "A treeless literals section repeats the Huffman table of an earlier block or dictionary, but none was decoded."
+        )
+    )]
+    TreelessWithoutTable,
+
     #[error("Missing modes")]
     #[diagnostic(
         code(rzstd::decompress::missing_modes),
@@ -186,6 +227,34 @@ pub enum Error {
     )]
     CopiedSizeOutOfBounds,
 
+    #[error("{0} trailing bytes after the last frame")]
+    #[diagnostic(
+        code(rzstd::decompress::trailing_garbage),
+        help("The input ends with bytes too short to start another frame.")
+    )]
+    TrailingGarbage(usize),
+
+    #[error("Input ends in the middle of a frame")]
+    #[diagnostic(
+        code(rzstd::decompress::truncated_frame),
+        help("The input was cut short, e.g. by an interrupted download or write.")
+    )]
+    TruncatedFrame,
+
+    #[error("{0} sequences exceed the sequences buffer")]
+    #[diagnostic(
+        code(rzstd::decompress::too_many_sequences),
+        help("The sequences section header declares more sequences than a block can hold.")
+    )]
+    TooManySequences(u32),
+
+    #[error("Frame needs dictionary {frame:x}, but {loaded:x} is loaded")]
+    #[diagnostic(
+        code(rzstd::decompress::wrong_dictionary),
+        help("The frame was compressed against a different dictionary than the decoder was given.")
+    )]
+    WrongDictionary { frame: u32, loaded: u32 },
+
     #[error(transparent)]
     #[diagnostic(code(rzstd::decompress::io))]
     IO(#[from] rzstd_io::Error),
@@ -199,6 +268,73 @@ pub enum Error {
     FSE(#[from] rzstd_fse::Error),
 }
 
+impl Error {
+    /// Whether the error stems from malformed or truncated input, as opposed
+    /// to a failing writer or source, a buffer too small for a valid
+    /// frame, a request the input can't satisfy, a limit or strictness
+    /// option the caller set, or output not matching its checksum.
+    pub fn is_corruption(&self) -> bool {
+        match self {
+            Self::InvalidMagicNum(_)
+            | Self::WindowSizeOutOfBounds(_)
+            | Self::ReservedBitSet
+            | Self::ReservedBlock(_)
+            | Self::InvalidBlockType(_)
+            | Self::BlockSizeOutOfBounds(_)
+            | Self::MissingCompressedSize
+            | Self::MissingHuffTable
+            | Self::TreelessWithoutTable
+            | Self::MissingModes
+            | Self::MissingSeqTable
+            | Self::MissingBlockSize
+            | Self::MissingFrameContentSize
+            | Self::LiteralsSizeTooLarge(_)
+            | Self::CompressedSizeTooLarge(_)
+            | Self::ExtraBitsInStream(_)
+            | Self::JumpTableError(_)
+            | Self::LiteralsBufferTooSmall
+            | Self::MissingTableForRepeat
+            | Self::EmptyRLESource
+            | Self::InvalidFSECode(_)
+            | Self::LiteralsBufferOverread { .. }
+            | Self::InvalidOffsetCode(_)
+            | Self::ZeroOffset
+            | Self::Corruption
+            | Self::CopiedSizeOutOfBounds
+            | Self::TruncatedFrame
+            | Self::TooManySequences(_)
+            | Self::Huff0(_)
+            | Self::FSE(_) => true,
+            Self::IO(rzstd_io::Error::IO(err)) => {
+                err.kind() == std::io::ErrorKind::UnexpectedEof
+            }
+            Self::IO(_) => true,
+            Self::ChecksumMismatch
+            | Self::WindowBufferTooSmall { .. }
+            | Self::OutputLimitExceeded(_)
+            | Self::FrameIndexOutOfRange(_)
+            | Self::UnusedBitSet
+            | Self::TrailingGarbage(_)
+            | Self::WrongDictionary { .. } => false,
+        }
+    }
+}
+
+impl Error {
+    /// Turns the source running dry, once a frame has started, into
+    /// [Error::TruncatedFrame].
+    pub(crate) fn mid_frame(self) -> Self {
+        match self {
+            Self::IO(rzstd_io::Error::IO(err))
+                if err.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                Self::TruncatedFrame
+            }
+            err => err,
+        }
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(value: std::io::Error) -> Self {
         Self::IO(rzstd_io::Error::IO(value))
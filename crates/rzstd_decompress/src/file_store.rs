@@ -0,0 +1,104 @@
+use memmap2::MmapMut;
+
+use crate::{WindowStore, prelude::*};
+
+/// A growable store backed by a memory-mapped temporary file, so that large
+/// windows live in the page cache, which the OS can write back to disk,
+/// rather than in RAM.
+///
+/// The file is deleted once the store is dropped.
+#[derive(Debug)]
+pub struct FileStore {
+    file: std::fs::File,
+    map: MmapMut,
+}
+
+impl FileStore {
+    /// A store of `len` bytes, at least one, in the OS's temporary directory.
+    pub fn new(len: usize) -> Result<Self, Error> {
+        Self::with_file(tempfile::tempfile()?, len)
+    }
+
+    /// Like [FileStore::new], in `dir`.
+    pub fn new_in(dir: impl AsRef<std::path::Path>, len: usize) -> Result<Self, Error> {
+        Self::with_file(tempfile::tempfile_in(dir)?, len)
+    }
+
+    fn with_file(file: std::fs::File, len: usize) -> Result<Self, Error> {
+        file.set_len(len.max(1) as u64)?;
+        let map = Self::map(&file)?;
+        Ok(Self { file, map })
+    }
+
+    fn map(file: &std::fs::File) -> Result<MmapMut, Error> {
+        // SAFETY: the file was created unlinked and is only reachable through
+        // the store, so nothing else resizes or writes to it while mapped.
+        Ok(unsafe { MmapMut::map_mut(file) }?)
+    }
+}
+
+impl WindowStore for FileStore {
+    #[inline(always)]
+    fn as_slice(&self) -> &[u8] {
+        &self.map
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.map
+    }
+
+    fn is_growable(&self) -> bool {
+        true
+    }
+
+    fn grow(&mut self, len: usize) -> Result<(), Error> {
+        // Both mappings share the file's pages, so what the old one held
+        // shows through the new one.
+        self.file.set_len(len as u64)?;
+        self.map = Self::map(&self.file)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecoderBuilder;
+
+    #[test]
+    fn test_file_store_grows() -> Result<(), Error> {
+        let mut store = FileStore::new(4)?;
+        store.as_mut_slice().copy_from_slice(b"head");
+
+        store.grow(1 << 16)?;
+        assert_eq!(store.as_slice().len(), 1 << 16);
+        assert_eq!(&store.as_slice()[..4], b"head");
+        assert!(store.as_slice()[4..].iter().all(|&b| b == 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_with_file_store() -> Result<(), Error> {
+        // Matches reaching back across most of a 4 MiB window.
+        let head = (0..3u32 << 20)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect::<Vec<_>>();
+        let content = [&head[..], &head[..1 << 20], b"tail"].concat();
+        let src = {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.window_log(22)?;
+            std::io::Write::write_all(&mut encoder, &content)?;
+            encoder.finish()?
+        };
+
+        let mut out = Vec::new();
+        DecoderBuilder::new()
+            .source(&src[..])
+            .window_store(FileStore::new(0)?, 0)
+            .build()
+            .decode(&mut out)?;
+        assert_eq!(out, content);
+        Ok(())
+    }
+}
@@ -1,9 +1,10 @@
-use crate::{WINDOW_SIZE_RANGE, prelude::*};
+use crate::{MAX_WINDOW_SIZE, MIN_WINDOW_SIZE, WINDOW_SIZE_RANGE, prelude::*};
 
 /// The frame header has a variable size, with a minimum of 2 bytes up to a
 /// maximum of 14 bytes depending on optional parameters.
 ///
 /// https://www.rfc-editor.org/rfc/rfc8878.pdf#name-frame-header
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Header {
     descriptor: HeaderDescriptor,
     window_descriptor: WindowDescriptor,
@@ -12,46 +13,120 @@ pub struct Header {
 }
 
 impl Header {
+    /// Starts building a header to be [written](Header::write) by an encoder.
+    pub fn builder() -> HeaderBuilder {
+        HeaderBuilder::default()
+    }
+
     pub fn read(src: &mut impl rzstd_io::Reader) -> Result<Self, Error> {
-        let descriptor = HeaderDescriptor::read(src.read_u8()?)?;
+        let descriptor = HeaderDescriptor(src.read_u8()?);
+        let mut consumed = 1;
 
         let window_descriptor = if descriptor.is_single_segment() {
             WindowDescriptor(0)
         } else {
+            consumed += 1;
             WindowDescriptor(src.read_u8()?)
         };
 
-        let dictionary_id = match descriptor.did_field_size() {
+        let dictionary_id = match descriptor.did_field_size()? {
             DIDFieldSize::Zero => None,
             size => {
                 let mut buf = [0u8; 4];
                 src.read_exact(&mut buf[..size.as_usize()])?;
+                consumed += size.as_usize();
                 Some(u32::from_le_bytes(buf))
             }
         };
 
-        let content_size = match descriptor.fcs_field_size() {
+        let content_size = match descriptor.fcs_field_size()? {
             FCSFieldSize::Zero => None,
             size => {
                 let mut buf = [0u8; 8];
                 src.read_exact(&mut buf[..size.as_usize()])?;
+                consumed += size.as_usize();
                 Some(u64::from_le_bytes(buf) + size.offset())
             }
         };
 
+        if consumed != descriptor.expected_header_len()? {
+            return Err(Error::Corruption);
+        }
+
         let header = Self {
             descriptor,
             window_descriptor,
             dictionary_id,
             content_size,
         };
-        if header.descriptor.is_single_segment() && header.content_size.is_none() {
-            return Err(Error::MissingFrameContentSize);
-        }
+        header.validate()?;
 
         Ok(header)
     }
 
+    /// Checks the invariants tying the header's fields together: the reserved
+    /// bit is clear, a single-segment frame declares its content size, the
+    /// window of any other frame is within [WINDOW_SIZE_RANGE], and the
+    /// optional fields are present exactly when the descriptor says so.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.descriptor.reserved_bit() != 0 {
+            return Err(Error::ReservedBitSet);
+        }
+
+        if self.descriptor.is_single_segment() {
+            if self.content_size.is_none() {
+                return Err(Error::MissingFrameContentSize);
+            }
+        } else {
+            let size = self.window_descriptor.size();
+            if !WINDOW_SIZE_RANGE.contains(&size) {
+                return Err(Error::WindowSizeOutOfBounds(size));
+            }
+        }
+
+        let has_dictionary_id =
+            !matches!(self.descriptor.did_field_size()?, DIDFieldSize::Zero);
+        let has_content_size =
+            !matches!(self.descriptor.fcs_field_size()?, FCSFieldSize::Zero);
+        if has_dictionary_id != self.dictionary_id.is_some()
+            || has_content_size != self.content_size.is_some()
+        {
+            return Err(Error::Corruption);
+        }
+
+        Ok(())
+    }
+
+    /// The size of a header starting with the `descriptor` byte, which it
+    /// includes.
+    pub(crate) fn size_for_descriptor(descriptor: u8) -> Result<usize, Error> {
+        HeaderDescriptor(descriptor).expected_header_len()
+    }
+
+    /// Writes the header as laid out by [Header::read], which reads it back
+    /// unchanged.
+    pub fn write(&self, w: &mut impl std::io::Write) -> std::io::Result<()> {
+        w.write_all(&[self.descriptor.0])?;
+
+        if !self.descriptor.is_single_segment() {
+            w.write_all(&[self.window_descriptor.0])?;
+        }
+
+        let descriptor = &self.descriptor;
+        let did_size = descriptor.did_field_size().map_err(std::io::Error::other)?;
+        if let Some(id) = self.dictionary_id {
+            w.write_all(&id.to_le_bytes()[..did_size.as_usize()])?;
+        }
+
+        let fcs_size = descriptor.fcs_field_size().map_err(std::io::Error::other)?;
+        if let Some(content_size) = self.content_size {
+            let field = content_size - fcs_size.offset();
+            w.write_all(&field.to_le_bytes()[..fcs_size.as_usize()])?;
+        }
+
+        Ok(())
+    }
+
     /// The original (uncompressed) size.
     pub fn content_size(&self) -> Option<u64> {
         self.content_size
@@ -60,6 +135,9 @@ impl Header {
     /// The ID of the dictionary required to properly decode the frame. When
     /// it's not present, it's up to the decoder to know which dictionary to
     /// use.
+    ///
+    /// A field that is present but holds zero reads as `Some(0)`, so callers
+    /// can tell it apart from a header without the field.
     pub fn dictionary_id(&self) -> Option<u32> {
         self.dictionary_id
     }
@@ -67,7 +145,7 @@ impl Header {
     /// Minimum memory buffer size to to decode compressed data.
     pub fn window_size(&self) -> Result<u64, Error> {
         if self.descriptor.is_single_segment() {
-            return Ok(self.content_size().unwrap());
+            return self.content_size().ok_or(Error::MissingFrameContentSize);
         }
 
         let size = self.window_descriptor.size();
@@ -78,10 +156,100 @@ impl Header {
         Ok(size)
     }
 
+    /// Whether the frame's content size doubles as its window size, all of
+    /// its output fitting in a single segment.
+    pub fn is_single_segment(&self) -> bool {
+        self.descriptor.is_single_segment()
+    }
+
     /// Whether the frame contains a 32-bit checksum at the end.
     pub fn has_checksum(&self) -> bool {
         self.descriptor.content_checksum_flag() == 1
     }
+
+    /// Whether the descriptor's unused bit is set. Encoders must leave it at
+    /// 0, but decoders may ignore it.
+    pub fn has_unused_bit(&self) -> bool {
+        self.descriptor.unused_bit() == 1
+    }
+}
+
+/// Builds a [Header], picking the smallest field able to hold each value.
+///
+/// Without a window size the frame is single-segment, in which case the
+/// content size is mandatory and doubles as the window size.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderBuilder {
+    content_size: Option<u64>,
+    window_size: Option<u64>,
+    dictionary_id: Option<u32>,
+    checksum: bool,
+}
+
+impl HeaderBuilder {
+    pub fn content_size(mut self, size: u64) -> Self {
+        self.content_size = Some(size);
+        self
+    }
+
+    /// Rounded up to the next size a [WindowDescriptor] can represent.
+    pub fn window_size(mut self, size: u64) -> Self {
+        self.window_size = Some(size);
+        self
+    }
+
+    pub fn dictionary_id(mut self, id: u32) -> Self {
+        self.dictionary_id = Some(id);
+        self
+    }
+
+    /// Whether the frame ends with a 32-bit content checksum.
+    pub fn checksum(mut self, enabled: bool) -> Self {
+        self.checksum = enabled;
+        self
+    }
+
+    pub fn build(self) -> Result<Header, Error> {
+        let window_descriptor = match self.window_size {
+            Some(size) if size > MAX_WINDOW_SIZE => {
+                return Err(Error::WindowSizeOutOfBounds(size));
+            }
+            Some(size) => WindowDescriptor::for_size(size),
+            None if self.content_size.is_none() => {
+                return Err(Error::MissingFrameContentSize);
+            }
+            None => WindowDescriptor(0),
+        };
+        let single_segment = self.window_size.is_none();
+
+        let fcs_flag = match self.content_size {
+            None => 0,
+            Some(0..=0xFF) if single_segment => 0,
+            Some(0x100..=0x1_00FF) => 1,
+            Some(0..=0xFFFF_FFFF) => 2,
+            Some(_) => 3,
+        };
+        let did_flag = match self.dictionary_id {
+            None => 0,
+            Some(0..=0xFF) => 1,
+            Some(0x100..=0xFFFF) => 2,
+            Some(_) => 3,
+        };
+
+        let descriptor = HeaderDescriptor(
+            (fcs_flag << 6)
+                | ((single_segment as u8) << 5)
+                | ((self.checksum as u8) << 2)
+                | did_flag,
+        );
+
+        Ok(Header {
+            descriptor,
+            window_descriptor,
+            dictionary_id: self.dictionary_id,
+            content_size: self.content_size,
+        })
+    }
 }
 
 /// The first header's byte is called the [HeaderDescriptor]. It describes which
@@ -98,23 +266,14 @@ impl Header {
 /// | 1-0        | Dictionary_ID_Flag      |
 ///
 /// (bit 7 is the highest bit, while bit 0 is the lowest one.)
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct HeaderDescriptor(u8);
 
 impl HeaderDescriptor {
-    fn read(val: u8) -> Result<Self, Error> {
-        let ret = Self(val);
-
-        if ret.reserved_bit() != 0 {
-            return Err(Error::ReservedBitSet);
-        }
-
-        Ok(ret)
-    }
-
     /// A 2-bit flag, specifying whether the [Header::content_size()]
     /// (decompressed data size) is provided within the header.
-    fn fcs_flag(&self) -> TwoBitFlag {
-        TwoBitFlag::from_u8(self.0 >> 6)
+    fn fcs_flag(&self) -> Result<TwoBitFlag, Error> {
+        TwoBitFlag::try_from_u8(self.0 >> 6).ok_or(Error::Corruption)
     }
 
     /// The number of bytes used by [Header::content_size()], derived from
@@ -131,8 +290,8 @@ impl HeaderDescriptor {
     /// [HeaderDescriptor::fcs_field_size()] is 1. Otherwise,
     /// [HeaderDescriptor::fcs_field_size()] is 0, and [Header::content_size()]
     /// is not provided.
-    fn fcs_field_size(&self) -> FCSFieldSize {
-        match self.fcs_flag() {
+    fn fcs_field_size(&self) -> Result<FCSFieldSize, Error> {
+        Ok(match self.fcs_flag()? {
             TwoBitFlag::Zero => {
                 if !self.is_single_segment() {
                     FCSFieldSize::Zero
@@ -143,7 +302,7 @@ impl HeaderDescriptor {
             TwoBitFlag::One => FCSFieldSize::Two,
             TwoBitFlag::Two => FCSFieldSize::Four,
             TwoBitFlag::Three => FCSFieldSize::Eight,
-        }
+        })
     }
 
     /// A bit flag, specifying whether data must be regenerated within a single
@@ -155,7 +314,7 @@ impl HeaderDescriptor {
     /// larger than [Header::content_size()].
     const fn single_segment_flag(&self) -> u8 {
         let val = (self.0 & 0x20) >> 5;
-        assert!(val == 0 || val == 1, "Invalid single segment flag");
+        debug_assert!(val == 0 || val == 1, "Invalid single segment flag");
         val
     }
 
@@ -163,10 +322,22 @@ impl HeaderDescriptor {
         self.single_segment_flag() == 1
     }
 
+    fn unused_bit(&self) -> u8 {
+        (self.0 & 0x10) >> 4
+    }
+
     fn reserved_bit(&self) -> u8 {
         (self.0 & 0x8) >> 3
     }
 
+    /// The size of the whole header, this descriptor included, as its flags
+    /// lay it out: 2 to 14 bytes.
+    fn expected_header_len(&self) -> Result<usize, Error> {
+        Ok(1 + usize::from(!self.is_single_segment())
+            + self.did_field_size()?.as_usize()
+            + self.fcs_field_size()?.as_usize())
+    }
+
     /// A bit flag, specifying whether a 32-bit [ContentChecksum] will be
     /// present at the frame's end.
     fn content_checksum_flag(&self) -> u8 {
@@ -175,8 +346,8 @@ impl HeaderDescriptor {
 
     /// A 2-bit flag, indicating whether a dictionary ID is provided within the
     /// header. It also specifies the size of this field
-    fn dictionary_id_flag(&self) -> TwoBitFlag {
-        TwoBitFlag::from_u8(self.0 & 0x03)
+    fn dictionary_id_flag(&self) -> Result<TwoBitFlag, Error> {
+        TwoBitFlag::try_from_u8(self.0 & 0x03).ok_or(Error::Corruption)
     }
 
     /// The number of bytes used by [Header::dictionary_id()], derived from
@@ -185,13 +356,13 @@ impl HeaderDescriptor {
     /// | dictionary_id  | 0 | 1 | 2 | 3 |
     /// |----------------|---|---|---|---|
     /// | did_field_size | 0 | 1 | 2 | 4 |
-    fn did_field_size(&self) -> DIDFieldSize {
-        match self.dictionary_id_flag() {
+    fn did_field_size(&self) -> Result<DIDFieldSize, Error> {
+        Ok(match self.dictionary_id_flag()? {
             TwoBitFlag::Zero => DIDFieldSize::Zero,
             TwoBitFlag::One => DIDFieldSize::One,
             TwoBitFlag::Two => DIDFieldSize::Two,
             TwoBitFlag::Three => DIDFieldSize::Four,
-        }
+        })
     }
 }
 
@@ -250,18 +421,19 @@ impl DIDFieldSize {
 /// | Bit number | 7-3      | 2-0      |
 /// |------------|----------|----------|
 /// | Field name | exponent | mantissa |
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct WindowDescriptor(u8);
 
 impl WindowDescriptor {
     const fn exponent(&self) -> u8 {
         let val = self.0 >> 3;
-        assert!(val < 0x20, "Exponent is 5 bits");
+        debug_assert!(val < 0x20, "Exponent is 5 bits");
         val
     }
 
     const fn mantissa(&self) -> u8 {
         let val = self.0 & 0x7;
-        assert!(val < 0x8, "Mantissa is 3 bits");
+        debug_assert!(val < 0x8, "Mantissa is 3 bits");
         val
     }
 
@@ -271,4 +443,240 @@ impl WindowDescriptor {
         let window_add = (window_base >> 3) * self.mantissa() as u64;
         window_base + window_add
     }
+
+    /// The smallest descriptor whose [WindowDescriptor::size()] is at least
+    /// `window_size`. Sizes below [WINDOW_SIZE_RANGE] round up to its start,
+    /// and callers must reject those above it.
+    fn for_size(window_size: u64) -> Self {
+        debug_assert!(window_size <= MAX_WINDOW_SIZE, "Window size too large");
+        let window_size = window_size.max(MIN_WINDOW_SIZE);
+
+        let mut window_log = window_size.ilog2() as u64;
+        let window_base = 1 << window_log;
+        let mut mantissa = (window_size - window_base).div_ceil(window_base >> 3);
+        if mantissa == 8 {
+            window_log += 1;
+            mantissa = 0;
+        }
+
+        Self((((window_log - 10) << 3) | mantissa) as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn round_trip(header: &Header) -> Header {
+        let mut buf = Vec::new();
+        header
+            .write(&mut buf)
+            .expect("writing to a Vec cannot fail");
+
+        let mut src = &buf[..];
+        let read = Header::read(&mut src).expect("written header should read back");
+        assert!(src.is_empty(), "{} trailing bytes", src.len());
+        read
+    }
+
+    #[test]
+    fn missing_content_size() {
+        assert!(matches!(
+            Header::builder().build(),
+            Err(Error::MissingFrameContentSize)
+        ));
+    }
+
+    #[test]
+    fn truncated_single_segment_header() {
+        // Single-segment with a 4-byte content size, of which only 2 remain.
+        let src = [0xA0, 0x10, 0x27];
+        for len in 1..=src.len() {
+            let res = Header::read(&mut &src[..len]);
+            assert!(
+                matches!(&res, Err(Error::IO(rzstd_io::Error::IO(err)))
+                    if err.kind() == std::io::ErrorKind::UnexpectedEof),
+                "len={len}: {res:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn explicit_zero_dictionary_id() {
+        // Single-segment, 1-byte dictionary ID of 0, content size 5.
+        let header = Header::read(&mut &[0x21, 0x00, 0x05][..]).unwrap();
+        assert_eq!(header.dictionary_id(), Some(0));
+        assert_eq!(header.content_size(), Some(5));
+
+        let header = Header::read(&mut &[0x20, 0x05][..]).unwrap();
+        assert_eq!(header.dictionary_id(), None);
+    }
+
+    #[test]
+    fn expected_header_len() {
+        for descriptor in (0..=u8::MAX).filter(|d| d & 0x08 == 0) {
+            let descriptor = HeaderDescriptor(descriptor);
+            let len = descriptor.expected_header_len().unwrap();
+            assert!((2..=14).contains(&len), "{descriptor:?}: {len}");
+
+            // A 1 MiB window if there's one, the other fields all ones, and
+            // bytes past the header that mustn't be read.
+            let mut src = vec![descriptor.0];
+            if !descriptor.is_single_segment() {
+                src.push(0x50);
+            }
+            src.resize(len, 0x01);
+            src.extend([0xEE; 3]);
+
+            let mut r = &src[..];
+            let header = Header::read(&mut r).unwrap();
+            assert_eq!(src.len() - r.len(), len, "{descriptor:?}");
+            assert_eq!(header.dictionary_id().is_some(), descriptor.0 & 0x03 != 0);
+            assert_eq!(
+                header.content_size().is_some(),
+                descriptor.is_single_segment() || descriptor.0 >> 6 != 0
+            );
+        }
+
+        // No flags at all: just the descriptor and the window.
+        assert_eq!(HeaderDescriptor(0x00).expected_header_len().unwrap(), 2);
+        // Single-segment with the largest fields: no window, 4-byte
+        // dictionary ID, 8-byte content size.
+        assert_eq!(HeaderDescriptor(0xE3).expected_header_len().unwrap(), 13);
+        // The same without single-segment: a window byte on top.
+        assert_eq!(HeaderDescriptor(0xC3).expected_header_len().unwrap(), 14);
+        // Single-segment without a content size flag still has its 1 byte.
+        assert_eq!(HeaderDescriptor(0x21).expected_header_len().unwrap(), 3);
+    }
+
+    #[test]
+    fn window_size_out_of_bounds() {
+        assert!(matches!(
+            Header::builder().window_size(MAX_WINDOW_SIZE + 1).build(),
+            Err(Error::WindowSizeOutOfBounds(_))
+        ));
+    }
+
+    #[test]
+    fn validate_reserved_bit() {
+        // Single-segment, reserved bit set, content size 5.
+        assert!(matches!(
+            Header::read(&mut &[0x28, 0x05][..]),
+            Err(Error::ReservedBitSet)
+        ));
+    }
+
+    #[test]
+    fn validate_window_size() {
+        // Window exponent 31, far above the largest window.
+        assert!(matches!(
+            Header::read(&mut &[0x00, 0xFF][..]),
+            Err(Error::WindowSizeOutOfBounds(_))
+        ));
+    }
+
+    #[test]
+    fn validate_fields_match_descriptor() {
+        let header = Header::builder().content_size(5).build().unwrap();
+        assert!(header.validate().is_ok());
+
+        let without_content_size = Header {
+            content_size: None,
+            ..header.clone()
+        };
+        assert!(matches!(
+            without_content_size.validate(),
+            Err(Error::MissingFrameContentSize)
+        ));
+
+        let with_dictionary_id = Header {
+            dictionary_id: Some(1),
+            ..header.clone()
+        };
+        assert!(matches!(
+            with_dictionary_id.validate(),
+            Err(Error::Corruption)
+        ));
+
+        let header = Header::builder().window_size(1 << 20).build().unwrap();
+        let with_content_size = Header {
+            content_size: Some(5),
+            ..header
+        };
+        assert!(matches!(
+            with_content_size.validate(),
+            Err(Error::Corruption)
+        ));
+    }
+
+    #[test]
+    fn window_descriptor_for_size() {
+        assert_eq!(WindowDescriptor::for_size(0).size(), MIN_WINDOW_SIZE);
+        assert_eq!(
+            WindowDescriptor::for_size(MAX_WINDOW_SIZE).size(),
+            MAX_WINDOW_SIZE
+        );
+
+        // Every representable size maps back to its own descriptor.
+        for desc in (0..=u8::MAX).map(WindowDescriptor) {
+            if desc.size() <= MAX_WINDOW_SIZE {
+                assert_eq!(WindowDescriptor::for_size(desc.size()), desc);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn window_descriptor_is_minimal(size in WINDOW_SIZE_RANGE) {
+            let desc = WindowDescriptor::for_size(size);
+            prop_assert!(desc.size() >= size);
+
+            let smallest = (0..=u8::MAX)
+                .map(WindowDescriptor)
+                .filter(|d| d.size() >= size)
+                .min_by_key(WindowDescriptor::size)
+                .unwrap();
+            prop_assert_eq!(desc.size(), smallest.size());
+        }
+
+        #[test]
+        fn header_round_trip(
+            content_size in prop::option::of(prop_oneof![
+                0..0x200u64,
+                0x1_0000..0x1_0200u64,
+                0xFFFF_FF00..0x1_0000_0100u64,
+                any::<u64>(),
+            ]),
+            window_size in prop::option::of(WINDOW_SIZE_RANGE),
+            dictionary_id in prop::option::of(any::<u32>()),
+            checksum in any::<bool>(),
+        ) {
+            let mut builder = Header::builder().checksum(checksum);
+            if let Some(size) = content_size {
+                builder = builder.content_size(size);
+            }
+            if let Some(size) = window_size {
+                builder = builder.window_size(size);
+            }
+            if let Some(id) = dictionary_id {
+                builder = builder.dictionary_id(id);
+            }
+
+            let Ok(header) = builder.build() else {
+                prop_assert!(content_size.is_none() && window_size.is_none());
+                return Ok(());
+            };
+
+            prop_assert_eq!(round_trip(&header), header.clone());
+            prop_assert_eq!(header.content_size(), content_size);
+            prop_assert_eq!(header.dictionary_id(), dictionary_id);
+            prop_assert_eq!(header.has_checksum(), checksum);
+            match window_size {
+                Some(size) => prop_assert!(header.window_size().unwrap() >= size),
+                None => prop_assert_eq!(header.window_size().ok(), content_size),
+            }
+        }
+    }
 }
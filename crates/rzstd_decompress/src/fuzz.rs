@@ -0,0 +1,54 @@
+use crate::{Decoder, MAX_BLOCK_SIZE};
+
+/// Window size [fuzz_decode] decodes with; frames asking for more are
+/// rejected rather than allocated for.
+const FUZZ_WINDOW_SIZE: usize = 1 << 20;
+
+/// Decodes arbitrary bytes, discarding the output.
+///
+/// Meant to be called from a `cargo-fuzz` target: malformed input must come
+/// back as an [Error](crate::Error), so any panic here is a bug.
+pub fn fuzz_decode(data: &[u8]) {
+    let mut window_buf = vec![0; FUZZ_WINDOW_SIZE + MAX_BLOCK_SIZE as usize];
+    let _ = Decoder::new(data, &mut window_buf, FUZZ_WINDOW_SIZE).decode(std::io::sink());
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn sample_frame() -> Vec<u8> {
+        let content = (0..20_000u32)
+            .map(|i| {
+                b"the quick brown fox jumps over the lazy dog"[(i * i % 43) as usize]
+            })
+            .collect::<Vec<_>>();
+        zstd::encode_all(&content[..], 3).expect("compressing into a Vec cannot fail")
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(10000))]
+
+        #[test]
+        fn test_fuzz_arbitrary(data in proptest::collection::vec(any::<u8>(), 0..512)) {
+            fuzz_decode(&data);
+        }
+
+        #[test]
+        fn test_fuzz_mutated_frame(
+            edits in proptest::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+            truncate in any::<prop::sample::Index>(),
+        ) {
+            let mut data = sample_frame();
+            for (idx, byte) in edits {
+                let idx = idx.index(data.len());
+                data[idx] = byte;
+            }
+            data.truncate(truncate.index(data.len() + 1));
+
+            fuzz_decode(&data);
+        }
+    }
+}
@@ -1,21 +1,45 @@
 use rzstd_foundation::const_assert;
 
+mod analyze;
 mod block;
 mod context;
 mod decoder;
+mod dictionary;
 mod errors;
-mod frame;
+#[cfg(feature = "file-store")]
+mod file_store;
+pub mod frame;
+mod fuzz;
 mod literals_section;
 mod prelude;
+mod push;
+mod scan;
 mod sequence_execution;
 mod sequences_section;
 mod window;
+mod window_store;
 
-pub use decoder::Decoder;
+pub use analyze::{Analysis, FrameStats, Summary, analyze, summarize};
+pub use decoder::{Decoder, DecoderBuilder, decompress};
 pub use errors::Error;
+#[cfg(feature = "file-store")]
+pub use file_store::FileStore;
+pub use fuzz::fuzz_decode;
+pub use literals_section::decode_literals;
+pub use push::PushDecoder;
+pub use scan::{
+    check_structure, decode_frame_at, frame_boundaries, required_window_buffer,
+};
+pub use sequences_section::Sequence;
+pub use window_store::WindowStore;
 
 pub const MAGIC_NUM: u32 = 0xFD2F_B528;
 
+/// Skippable frames start with any of these 16 magic numbers, followed by the
+/// 4-byte little-endian size of their user data.
+pub const SKIPPABLE_MAGIC_NUM_RANGE: std::ops::RangeInclusive<u32> =
+    0x184D_2A50..=0x184D_2A5F;
+
 pub const MIN_WINDOW_SIZE: u64 = 0x400;
 pub const MAX_WINDOW_SIZE: u64 = 128 * 1024 * 1024;
 pub const WINDOW_SIZE_RANGE: std::ops::RangeInclusive<u64> =
@@ -25,6 +49,7 @@ pub const MAX_BLOCK_SIZE: u32 = 128 * 1024;
 
 pub const LL_DIST: DefaultDistribution = DefaultDistribution {
     accuracy_log: 9,
+    max_symbols: 36,
     predefined_accuracy_log: 6,
     predefined_table: &[
         4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2,
@@ -35,6 +60,7 @@ const_assert!(LL_DIST.predefined_table().len() == 36);
 
 pub const ML_DIST: DefaultDistribution = DefaultDistribution {
     accuracy_log: 9,
+    max_symbols: 53,
     predefined_accuracy_log: 6,
     predefined_table: &[
         1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
@@ -46,6 +72,7 @@ const_assert!(ML_DIST.predefined_table().len() == 53);
 
 pub const OF_DIST: DefaultDistribution = DefaultDistribution {
     accuracy_log: 8,
+    max_symbols: 32,
     predefined_accuracy_log: 5,
     predefined_table: &[
         1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1,
@@ -56,12 +83,23 @@ const_assert!(OF_DIST.predefined_table().len() == 29);
 
 pub struct DefaultDistribution {
     accuracy_log: usize,
+    /// Number of codes the symbol type defines.
+    max_symbols: usize,
     predefined_accuracy_log: usize,
     predefined_table: &'static [i16],
 }
 
 impl DefaultDistribution {
     pub const fn accuracy_log(&self) -> usize {
+        self.accuracy_log
+    }
+
+    /// Tables read from the stream may declare no more symbols than this.
+    pub const fn max_symbols(&self) -> usize {
+        self.max_symbols
+    }
+
+    pub const fn predefined_accuracy_log(&self) -> usize {
         self.predefined_accuracy_log
     }
 
@@ -70,6 +108,55 @@ impl DefaultDistribution {
     }
 
     pub const fn predefined_table(&self) -> &'static [i16] {
-        &self.predefined_table
+        self.predefined_table
+    }
+
+    /// Builds the decoding table of the predefined distribution, as used by
+    /// sequences sections in predefined mode. Panics if `N` isn't
+    /// [DefaultDistribution::table_size()], which no input can cause.
+    pub fn build_table<const N: usize>(
+        &self,
+    ) -> Result<rzstd_fse::DecodingTable<N>, Error> {
+        assert_eq!(N, self.table_size(), "table size mismatch");
+
+        let mut norm = rzstd_fse::NormalizedDistribution::<N>::from_predefined(
+            self.predefined_table,
+            self.predefined_accuracy_log as u8,
+        )?;
+        Ok(rzstd_fse::DecodingTable::from_distribution(&mut norm)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_build_table<const N: usize>(dist: DefaultDistribution) -> Result<(), Error> {
+        let table = dist.build_table::<N>()?;
+
+        let mut norm = rzstd_fse::NormalizedDistribution::<N>::from_predefined(
+            dist.predefined_table(),
+            dist.predefined_accuracy_log() as u8,
+        )?;
+        let expected = rzstd_fse::DecodingTable::<N>::from_distribution(&mut norm)?;
+
+        for state in 0..1 << dist.predefined_accuracy_log() {
+            assert_eq!(table.entry(state), expected.entry(state), "state={state}");
+        }
+        assert_eq!(table.entry(1 << dist.predefined_accuracy_log()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_table() -> Result<(), Error> {
+        check_build_table::<{ LL_DIST.table_size() }>(LL_DIST)?;
+        check_build_table::<{ ML_DIST.table_size() }>(ML_DIST)?;
+        check_build_table::<{ OF_DIST.table_size() }>(OF_DIST)
+    }
+
+    #[test]
+    #[should_panic(expected = "table size mismatch")]
+    fn test_build_table_size_mismatch() {
+        let _ = OF_DIST.build_table::<512>();
     }
 }
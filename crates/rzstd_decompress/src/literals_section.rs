@@ -1,4 +1,6 @@
-use crate::{MAX_BLOCK_SIZE, context::Context, prelude::*};
+use crate::{
+    MAX_BLOCK_SIZE, MIN_WINDOW_SIZE, context::Context, prelude::*, window::Window,
+};
 
 const RAW_RLE_BUF_SIZE: [Option<usize>; 4] = [None, Some(1), None, Some(2)];
 const RAW_RLE_SHIFT: [usize; 4] = [3, 4, 3, 4];
@@ -7,8 +9,26 @@ const COMPRESSED_BITS: [usize; 4] = [10, 10, 14, 18];
 const COMPRESSED_STREAMS: [Streams; 4] =
     [Streams::One, Streams::Four, Streams::Four, Streams::Four];
 
+/// Literal sections regenerating at least this many bytes get their four
+/// Huffman streams decoded on separate threads.
+const PARALLEL_LITERALS_THRESHOLD: usize = 32 * 1024;
+
+/// Names of the four Huffman streams, as reported in errors.
+const STREAM_LABELS: [&str; 4] = ["stream 1", "stream 2", "stream 3", "stream 4"];
+
 impl<R: rzstd_io::Reader> Context<'_, R> {
     pub fn literals_section(&mut self) -> Result<u32, Error> {
+        self.block_literals(None)
+    }
+
+    /// Decodes the literals section of a compressed block of `block_size`
+    /// bytes, or of an unknown block if `None`, into [Context::literals_buf].
+    ///
+    /// Raw literals followed only by the 1-byte header of an empty sequences
+    /// section make up the block's whole output, so they are read straight
+    /// into the window instead, leaving nothing for
+    /// [Context::execute_sequences] to copy.
+    pub fn block_literals(&mut self, block_size: Option<u32>) -> Result<u32, Error> {
         let (header, read) = Header::read(&mut self.src)?;
         if header.regenerated_size > MAX_BLOCK_SIZE {
             return Err(Error::LiteralsSizeTooLarge(header.regenerated_size));
@@ -25,7 +45,22 @@ impl<R: rzstd_io::Reader> Context<'_, R> {
             },
         } as usize;
 
-        let dst = &mut self.literals_buf[..header.regenerated_size as usize];
+        let literals_only = header.ls_type == Type::Raw
+            && block_size == Some((read + literals_size + 1) as u32);
+        if literals_only {
+            if literals_size > self.window_buf.size() {
+                return Err(Error::BlockSizeOutOfBounds(literals_size as u32));
+            }
+
+            self.window_buf.read_from(&mut self.src, literals_size)?;
+            self.literals_idx = 0;
+            return Ok((literals_size + read) as u32);
+        }
+
+        let dst = self
+            .literals_buf
+            .get_mut(..header.regenerated_size as usize)
+            .ok_or(Error::LiteralsBufferTooSmall)?;
         self.literals_idx = header.regenerated_size as usize;
         match header.ls_type {
             Type::Raw => {
@@ -43,8 +78,12 @@ impl<R: rzstd_io::Reader> Context<'_, R> {
                 if compressed_size > MAX_BLOCK_SIZE {
                     return Err(Error::CompressedSizeTooLarge(compressed_size));
                 }
+                // Even a single regenerated byte takes a non-empty stream.
+                if compressed_size == 0 && header.regenerated_size > 0 {
+                    return Err(Error::Corruption);
+                }
 
-                let scratch = &mut self.scratch_buf[..literals_size as usize];
+                let scratch = &mut self.scratch_buf[..literals_size];
                 self.src.read_exact(scratch)?;
 
                 let read = if header.ls_type == Type::Compressed {
@@ -55,13 +94,31 @@ impl<R: rzstd_io::Reader> Context<'_, R> {
                     0
                 };
 
-                let table = self.huff.table.as_ref().ok_or(Error::MissingHuffTable)?;
+                let table = self.huff.table.as_ref().ok_or(match header.ls_type {
+                    Type::Treeless => Error::TreelessWithoutTable,
+                    _ => Error::MissingHuffTable,
+                })?;
                 Self::huff_streams(&scratch[read..], dst, table, header.streams)?;
             }
         };
         Ok((literals_size + read) as u32)
     }
 
+    /// Decodes the literals section [Context::src] is positioned at, leaving
+    /// the sequences after it unread, and returns the regenerated literals.
+    ///
+    /// Meant for inspecting a compressed block's literals. The Huffman table
+    /// a compressed section carries is only used for this call: later
+    /// treeless sections keep repeating the one decoded before it.
+    pub fn decode_literals_only(&mut self) -> Result<&[u8], Error> {
+        let table = self.huff.table.clone();
+        let res = self.literals_section();
+        self.huff.table = table;
+        res?;
+
+        Ok(&self.literals_buf[..self.literals_idx])
+    }
+
     fn huff_streams(
         src: &[u8],
         dst: &mut [u8],
@@ -69,89 +126,177 @@ impl<R: rzstd_io::Reader> Context<'_, R> {
         streams: Streams,
     ) -> Result<(), Error> {
         match streams {
-            Streams::One => {
-                let mut r = rzstd_io::ReverseBitReader::new(src)?;
-                let mut decoder = rzstd_huff0::Decoder::new(table, &mut r);
+            Streams::One => Self::huff_stream(src, dst, table, "literals stream"),
+            Streams::Four if dst.len() >= PARALLEL_LITERALS_THRESHOLD => {
+                Self::huff_streams_parallel(src, dst, table)
+            }
+            Streams::Four => Self::huff_streams_serial(src, dst, table),
+        }
+    }
 
-                for d in dst.iter_mut() {
-                    *d = decoder.decode(&mut r);
-                }
+    fn huff_streams_serial(
+        src: &[u8],
+        dst: &mut [u8],
+        table: &rzstd_huff0::DecodingTable,
+    ) -> Result<(), Error> {
+        let [s0, s1, s2, s3] = Self::jump_table(src)?;
+        let mut readers = [
+            rzstd_io::ReverseBitReader::new_named(s0, STREAM_LABELS[0])?,
+            rzstd_io::ReverseBitReader::new_named(s1, STREAM_LABELS[1])?,
+            rzstd_io::ReverseBitReader::new_named(s2, STREAM_LABELS[2])?,
+            rzstd_io::ReverseBitReader::new_named(s3, STREAM_LABELS[3])?,
+        ];
+
+        let [out0, out1, out2, out3] = Self::split_four(dst)?;
+        let chunk = out0.len();
+        let last_chunk_size = out3.len();
+
+        let mut decoder0 = rzstd_huff0::Decoder::new(table, &mut readers[0]);
+        let mut decoder1 = rzstd_huff0::Decoder::new(table, &mut readers[1]);
+        let mut decoder2 = rzstd_huff0::Decoder::new(table, &mut readers[2]);
+        let mut decoder3 = rzstd_huff0::Decoder::new(table, &mut readers[3]);
+
+        let burst_len = chunk.min(last_chunk_size);
+        for i in 0..burst_len {
+            out0[i] = decoder0.decode(&mut readers[0]);
+            out1[i] = decoder1.decode(&mut readers[1]);
+            out2[i] = decoder2.decode(&mut readers[2]);
+            out3[i] = decoder3.decode(&mut readers[3]);
+        }
 
-                if r.bits_remaining() > 0 {
-                    return Err(Error::ExtraBitsInStream(r.bits_remaining()));
-                }
+        if chunk > burst_len {
+            for i in burst_len..chunk {
+                out0[i] = decoder0.decode(&mut readers[0]);
+                out1[i] = decoder1.decode(&mut readers[1]);
+                out2[i] = decoder2.decode(&mut readers[2]);
+            }
+        }
 
-                Ok(())
+        for r in readers.iter() {
+            if r.bits_remaining() > 0 {
+                return Err(Error::ExtraBitsInStream(r.bits_remaining()));
             }
-            Streams::Four => {
-                if src.len() < 6 {
-                    return Err(Error::JumpTableError(
-                        "Source too short for jump table".into(),
-                    ));
-                }
+        }
 
-                let mut readers = {
-                    let s0 = src[0] as usize + ((src[1] as usize) << 8);
-                    let s1 = s0 + src[2] as usize + ((src[3] as usize) << 8);
-                    let s2 = s1 + src[4] as usize + ((src[5] as usize) << 8);
-
-                    if s2 > src.len() {
-                        return Err(Error::JumpTableError(
-                            "Jump table offsets exceed source length".into(),
-                        ));
-                    }
-
-                    let src = &src[6..];
-
-                    [
-                        rzstd_io::ReverseBitReader::new(&src[..s0])?,
-                        rzstd_io::ReverseBitReader::new(&src[s0..s1])?,
-                        rzstd_io::ReverseBitReader::new(&src[s1..s2])?,
-                        rzstd_io::ReverseBitReader::new(&src[s2..])?,
-                    ]
-                };
+        Ok(())
+    }
 
-                let chunk = (dst.len() + 3) / 4;
-                let last_chunk_size = dst.len() - (chunk * 3);
+    /// Decodes the four streams on separate threads, each straight into its
+    /// own segment of `dst`.
+    ///
+    /// The streams are independent, so this only pays off once they are long
+    /// enough to amortize spawning the threads; see
+    /// [PARALLEL_LITERALS_THRESHOLD].
+    fn huff_streams_parallel(
+        src: &[u8],
+        dst: &mut [u8],
+        table: &rzstd_huff0::DecodingTable,
+    ) -> Result<(), Error> {
+        let [s0, s1, s2, s3] = Self::jump_table(src)?;
+        let [out0, out1, out2, out3] = Self::split_four(dst)?;
+
+        std::thread::scope(|scope| {
+            let handles = [
+                (s0, out0, STREAM_LABELS[0]),
+                (s1, out1, STREAM_LABELS[1]),
+                (s2, out2, STREAM_LABELS[2]),
+            ]
+            .map(|(src, dst, label)| {
+                scope.spawn(move || Self::huff_stream(src, dst, table, label))
+            });
+
+            let last = Self::huff_stream(s3, out3, table, STREAM_LABELS[3]);
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("literals decoding thread panicked"))
+                .chain(std::iter::once(last))
+                .collect::<Result<(), Error>>()
+        })
+    }
 
-                if dst.len() < 3 * chunk {
-                    return Err(Error::LiteralsBufferTooSmall);
-                }
+    fn huff_stream(
+        src: &[u8],
+        dst: &mut [u8],
+        table: &rzstd_huff0::DecodingTable,
+        label: &'static str,
+    ) -> Result<(), Error> {
+        let mut r = rzstd_io::ReverseBitReader::new_named(src, label)?;
+        let mut decoder = rzstd_huff0::Decoder::new(table, &mut r);
 
-                let (out0, rem) = dst.split_at_mut(chunk);
-                let (out1, rem) = rem.split_at_mut(chunk);
-                let (out2, out3) = rem.split_at_mut(chunk);
-
-                let mut decoder0 = rzstd_huff0::Decoder::new(table, &mut readers[0]);
-                let mut decoder1 = rzstd_huff0::Decoder::new(table, &mut readers[1]);
-                let mut decoder2 = rzstd_huff0::Decoder::new(table, &mut readers[2]);
-                let mut decoder3 = rzstd_huff0::Decoder::new(table, &mut readers[3]);
-
-                let burst_len = chunk.min(last_chunk_size);
-                for i in 0..burst_len {
-                    out0[i] = decoder0.decode(&mut readers[0]);
-                    out1[i] = decoder1.decode(&mut readers[1]);
-                    out2[i] = decoder2.decode(&mut readers[2]);
-                    out3[i] = decoder3.decode(&mut readers[3]);
-                }
+        for d in dst.iter_mut() {
+            *d = decoder.decode(&mut r);
+        }
 
-                if chunk > burst_len {
-                    for i in burst_len..chunk {
-                        out0[i] = decoder0.decode(&mut readers[0]);
-                        out1[i] = decoder1.decode(&mut readers[1]);
-                        out2[i] = decoder2.decode(&mut readers[2]);
-                    }
-                }
+        if r.bits_remaining() > 0 {
+            return Err(Error::ExtraBitsInStream(r.bits_remaining()));
+        }
 
-                for r in readers.iter() {
-                    if r.bits_remaining() > 0 {
-                        return Err(Error::ExtraBitsInStream(r.bits_remaining()));
-                    }
-                }
+        Ok(())
+    }
+
+    /// Splits the regenerated literals into the segments of each of the four
+    /// streams: three of `ceil(len / 4)` bytes, and the remainder.
+    /// Splits the regenerated literals into the outputs of the four streams:
+    /// three of `len.div_ceil(4)` bytes, and the rest for the last one.
+    ///
+    /// Some sizes below 6 leave nothing for the last stream even to start
+    /// from, so a section declaring them can't have been split into four.
+    fn split_four(dst: &mut [u8]) -> Result<[&mut [u8]; 4], Error> {
+        let chunk = dst.len().div_ceil(4);
+        if dst.len() < 3 * chunk {
+            return Err(Error::JumpTableError(format!(
+                "{} literals can't be split into four streams",
+                dst.len()
+            )));
+        }
+
+        let (out0, rem) = dst.split_at_mut(chunk);
+        let (out1, rem) = rem.split_at_mut(chunk);
+        let (out2, out3) = rem.split_at_mut(chunk);
+        Ok([out0, out1, out2, out3])
+    }
+
+    /// Splits a 4-stream literals payload along its jump table.
+    ///
+    /// The jump table holds the little-endian sizes of the first three
+    /// streams; the fourth one takes whatever is left. Every stream must be
+    /// non-empty, since each one ends with a sentinel bit, and the errors name
+    /// the first stream that isn't, or that overruns the payload.
+    fn jump_table(src: &[u8]) -> Result<[&[u8]; 4], Error> {
+        const JUMP_TABLE_SIZE: usize = 6;
+
+        let Some((table, src)) = src.split_at_checked(JUMP_TABLE_SIZE) else {
+            return Err(Error::JumpTableError(format!(
+                "source of {} bytes is too short for the jump table",
+                src.len()
+            )));
+        };
+
+        let mut ends = [0; 3];
+        let mut end = 0;
+        for (i, chunk) in table.chunks_exact(2).enumerate() {
+            let size = u16::from_le_bytes([chunk[0], chunk[1]]) as usize;
+            if size == 0 {
+                return Err(Error::JumpTableError(format!("stream {} is empty", i + 1)));
+            }
 
-                Ok(())
+            end += size;
+            if end > src.len() {
+                return Err(Error::JumpTableError(format!(
+                    "stream {} would end at {end}, past the {} available bytes",
+                    i + 1,
+                    src.len()
+                )));
             }
+            ends[i] = end;
         }
+
+        if end == src.len() {
+            return Err(Error::JumpTableError("stream 4 is empty".into()));
+        }
+
+        let [s0, s1, s2] = ends;
+        Ok([&src[..s0], &src[s0..s1], &src[s1..s2], &src[s2..]])
     }
 }
 
@@ -183,8 +328,11 @@ impl Header {
     pub fn read(src: &mut impl rzstd_io::Reader) -> Result<(Header, usize), Error> {
         let first = src.read_u8()?;
 
-        let ls_type = Type::from(TwoBitFlag::from_u8(first & 0x03));
-        let size_format = TwoBitFlag::from_u8((first >> 2) & 0x3);
+        let ls_type = TwoBitFlag::try_from_u8(first & 0x03)
+            .map(Type::from)
+            .ok_or(Error::Corruption)?;
+        let size_format =
+            TwoBitFlag::try_from_u8((first >> 2) & 0x3).ok_or(Error::Corruption)?;
 
         match ls_type {
             Type::Raw | Type::RLE => {
@@ -206,7 +354,7 @@ impl Header {
         ls_type: Type,
         size_format: TwoBitFlag,
     ) -> Result<(Header, usize), Error> {
-        assert!(matches!(ls_type, Type::Raw | Type::RLE));
+        debug_assert!(matches!(ls_type, Type::Raw | Type::RLE));
 
         let buf_size = RAW_RLE_BUF_SIZE[size_format as usize];
         let header = {
@@ -240,7 +388,7 @@ impl Header {
         ls_type: Type,
         size_format: TwoBitFlag,
     ) -> Result<(Header, usize), Error> {
-        assert!(matches!(ls_type, Type::Compressed | Type::Treeless));
+        debug_assert!(matches!(ls_type, Type::Compressed | Type::Treeless));
 
         let buf_size = COMPRESSED_BUF_SIZE[size_format as usize];
         let n_bits = COMPRESSED_BITS[size_format as usize];
@@ -276,6 +424,7 @@ pub enum Streams {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Type {
     /// Literals are stored uncompressed
     Raw = 0,
@@ -306,3 +455,498 @@ impl From<TwoBitFlag> for Type {
         }
     }
 }
+
+/// Decodes the literals section a compressed block's content starts with,
+/// ignoring the sequences after it.
+///
+/// The block is decoded on its own, so a treeless literals section, which
+/// repeats the Huffman table of an earlier block, fails with
+/// [Error::TreelessWithoutTable].
+pub fn decode_literals(block: &[u8]) -> Result<Vec<u8>, Error> {
+    let window = Window::growable(MIN_WINDOW_SIZE as usize);
+    let mut ctx = Context::with_window(block, window);
+    Ok(ctx.decode_literals_only()?.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::context::LITERALS_BUF_SIZE;
+
+    type Ctx<'a> = Context<'a, &'a [u8]>;
+
+    /// A raw literals section regenerating `size` bytes, with a 3-byte header.
+    fn raw_literals(size: usize) -> Vec<u8> {
+        let header = ((size as u32) << 4) | (3 << 2);
+        [&header.to_le_bytes()[..3], &vec![0xAB; size]].concat()
+    }
+
+    #[test]
+    fn test_literals_buffer_boundary() -> Result<(), Error> {
+        let mut window = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+
+        let src = raw_literals(LITERALS_BUF_SIZE);
+        let mut ctx = Ctx::new(&src, &mut window, MAX_BLOCK_SIZE as usize);
+        ctx.literals_section()?;
+        assert_eq!(ctx.literals_idx, LITERALS_BUF_SIZE);
+
+        let src = raw_literals(LITERALS_BUF_SIZE + 1);
+        let mut ctx = Ctx::new(&src, &mut window, MAX_BLOCK_SIZE as usize);
+        assert!(matches!(
+            ctx.literals_section(),
+            Err(Error::LiteralsSizeTooLarge(size)) if size as usize == LITERALS_BUF_SIZE + 1
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_literals_buffer_too_small() {
+        let mut window = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+        let src = raw_literals(100);
+
+        let mut ctx = Ctx::new(&src, &mut window, MAX_BLOCK_SIZE as usize);
+        ctx.literals_buf.truncate(99);
+        assert!(matches!(
+            ctx.literals_section(),
+            Err(Error::LiteralsBufferTooSmall)
+        ));
+    }
+
+    #[test]
+    fn test_compressed_size_formats() -> Result<(), Error> {
+        // Size format, bits per size, then sizes using the top and bottom bits
+        // of their fields so that a misplaced shift or mask shows.
+        let cases = [
+            (0u64, 10, 0x3FF, 0x201),
+            (1, 10, 0x201, 0x3FF),
+            (2, 14, 0x3FFF, 0x2001),
+            (3, 18, 0x3FFFF, 0x20001),
+        ];
+
+        for (size_format, n_bits, regenerated, compressed) in cases {
+            for ls_type in [2u64, 3] {
+                let header = ls_type
+                    | (size_format << 2)
+                    | (regenerated << 4)
+                    | (compressed << (4 + n_bits));
+                let len = (4 + 2 * n_bits as usize).div_ceil(8);
+                let src = header.to_le_bytes();
+
+                let (header, read) = Header::read(&mut &src[..len])?;
+                assert_eq!(read, len);
+                assert_eq!(header.ls_type as u64, ls_type);
+                assert_eq!(header.regenerated_size as u64, regenerated);
+                assert_eq!(header.compressed_size, Some(compressed as u32));
+                match size_format {
+                    0 => assert!(matches!(header.streams, Streams::One)),
+                    _ => assert!(matches!(header.streams, Streams::Four)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rle_single_byte_header() -> Result<(), Error> {
+        let mut window = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+
+        // The size takes the 5 bits above the type and a single format bit,
+        // which reads as size format 0 or 2 depending on the size's low bit.
+        for size in [0usize, 20, 31] {
+            let src = [0x01 | (size << 3) as u8, 0x7A];
+
+            let (header, read) = Header::read(&mut &src[..])?;
+            assert_eq!(read, 1);
+            assert_eq!(header.ls_type, Type::RLE);
+            assert_eq!(header.regenerated_size as usize, size);
+            assert_eq!(header.compressed_size, None);
+
+            let mut ctx = Ctx::new(&src, &mut window, MAX_BLOCK_SIZE as usize);
+            assert_eq!(ctx.literals_section()?, 2);
+            assert_eq!(ctx.literals_buf[..ctx.literals_idx], vec![0x7A; size]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_compressed_literals() {
+        let mut window = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+
+        // Compressed and treeless sections regenerating 10 bytes out of none.
+        for ls_type in [2u32, 3] {
+            let header = ls_type | (10 << 4);
+            let src = header.to_le_bytes();
+            let mut ctx = Ctx::new(&src[..3], &mut window, MAX_BLOCK_SIZE as usize);
+            assert!(matches!(ctx.literals_section(), Err(Error::Corruption)));
+        }
+    }
+
+    #[test]
+    fn test_decode_literals_only() -> Result<(), Error> {
+        let literals = b"only the literals, please; ".repeat(30);
+        let compressed = rzstd_huff0::compress(&literals)?;
+
+        // Compressed, single stream, 10-bit sizes; then sequences, left unread.
+        let header = 2 | (literals.len() << 4) | (compressed.len() << 14);
+        let sequences = [0x01, 0x02, 0x03];
+        let src = [&header.to_le_bytes()[..3], &compressed, &sequences].concat();
+
+        let mut window = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+        let mut ctx = Ctx::new(&src, &mut window, MAX_BLOCK_SIZE as usize);
+        assert_eq!(ctx.decode_literals_only()?, literals);
+        assert!(ctx.huff.table.is_none());
+
+        let mut rest = Vec::new();
+        ctx.src.read_to_end(&mut rest)?;
+        assert_eq!(rest, sequences);
+
+        assert_eq!(decode_literals(&src)?, literals);
+        let treeless = 3u32 | (4 << 4) | (4 << 14);
+        let treeless = [&treeless.to_le_bytes()[..3], &[0xff; 4]].concat();
+        assert!(matches!(
+            decode_literals(&treeless),
+            Err(Error::TreelessWithoutTable)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_treeless_without_table() -> Result<(), Error> {
+        let mut window = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+
+        // A treeless section with no earlier Huffman table to repeat.
+        let header = 3u32 | (4 << 4) | (4 << 14);
+        let src = [&header.to_le_bytes()[..3], &[0xff; 4]].concat();
+        let mut ctx = Ctx::new(&src, &mut window, MAX_BLOCK_SIZE as usize);
+        assert!(matches!(
+            ctx.literals_section(),
+            Err(Error::TreelessWithoutTable)
+        ));
+
+        // A compressed section whose tree description is broken fails while
+        // reading it, rather than as a missing table.
+        let header = 2u32 | (4 << 4) | (4 << 14);
+        let src = [&header.to_le_bytes()[..3], &[0xff, 0, 0, 0]].concat();
+        let mut ctx = Ctx::new(&src, &mut window, MAX_BLOCK_SIZE as usize);
+        assert!(matches!(ctx.literals_section(), Err(Error::Huff0(_))));
+
+        // Once a compressed section provided a table, treeless ones reuse it.
+        let literals = b"treeless literals".repeat(4);
+        let compressed = rzstd_huff0::compress(&literals)?;
+        let (_, description_len) = rzstd_huff0::DecodingTable::<2048>::read(&compressed)?;
+        let stream = &compressed[description_len..];
+
+        let compressed_header = 2 | (literals.len() << 4) | (compressed.len() << 14);
+        let treeless_header = 3 | (literals.len() << 4) | (stream.len() << 14);
+        let src = [
+            &compressed_header.to_le_bytes()[..3],
+            &compressed,
+            &treeless_header.to_le_bytes()[..3],
+            stream,
+        ]
+        .concat();
+
+        let mut ctx = Ctx::new(&src, &mut window, MAX_BLOCK_SIZE as usize);
+        ctx.literals_section()?;
+        ctx.literals_section()?;
+        assert_eq!(ctx.literals_buf[..ctx.literals_idx], literals);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_treeless_reuses_table_across_blocks() -> Result<(), Error> {
+        let table = |alphabet: &[u8]| {
+            let mut histogram = [0u32; 256];
+            alphabet
+                .iter()
+                .enumerate()
+                .for_each(|(i, &b)| histogram[b as usize] = 1 << i);
+            rzstd_huff0::EncodingTable::from_histogram(&histogram)
+        };
+        let first = table(b"abcd")?;
+        let second = table(b"wxyz")?;
+
+        // A block holding nothing but a single-stream literals section, with
+        // the table's description if it isn't treeless.
+        let block =
+            |table: &rzstd_huff0::EncodingTable, treeless: bool, literals: &[u8]| {
+                let mut payload = Vec::new();
+                if !treeless {
+                    table.write_description(&mut payload)?;
+                }
+                table.encode(literals, &mut payload)?;
+
+                let ty = if treeless { 3 } else { 2 };
+                let header = ty | (literals.len() << 4) | (payload.len() << 14);
+                let content = [&header.to_le_bytes()[..3], &payload, &[0]].concat();
+                Ok::<_, Error>(content)
+            };
+
+        let literals: [&[u8]; 5] = [
+            b"abcdabcddddccb",
+            b"dcbadcbadddd",
+            b"aaaabbbbccccdddd",
+            b"wxyzzzyyx",
+            b"zyxwzyxwzzzz",
+        ];
+        let blocks = [
+            block(&first, false, literals[0])?,
+            block(&first, true, literals[1])?,
+            block(&first, true, literals[2])?,
+            // Replaces the table for the treeless block after it.
+            block(&second, false, literals[3])?,
+            block(&second, true, literals[4])?,
+        ];
+
+        let content = literals.concat();
+        let mut src = crate::MAGIC_NUM.to_le_bytes().to_vec();
+        crate::frame::Header::builder()
+            .content_size(content.len() as u64)
+            .build()?
+            .write(&mut src)?;
+        for (i, block) in blocks.iter().enumerate() {
+            let last = (i == blocks.len() - 1) as u32;
+            let header = last | (2 << 1) | ((block.len() as u32) << 3);
+            src.extend(&header.to_le_bytes()[..3]);
+            src.extend(block);
+        }
+
+        assert_eq!(crate::decompress(&src)?, content);
+        Ok(())
+    }
+
+    #[test]
+    fn test_too_few_literals_for_four_streams() {
+        for len in [1, 2, 5] {
+            let err = Ctx::split_four(&mut vec![0; len])
+                .expect_err("not enough literals for four streams");
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "Jump table error: {len} literals can't be split into four streams"
+                )
+            );
+        }
+
+        for len in [0, 3, 4, 6, 7] {
+            let mut dst = vec![0; len];
+            let outs = Ctx::split_four(&mut dst).expect("splits in four");
+            assert_eq!(outs.iter().map(|o| o.len()).sum::<usize>(), len);
+        }
+
+        // Four streams holding nothing but their sentinel.
+        let (table, _) = four_streams(b"abcdabcd");
+        let src = [1, 0, 1, 0, 1, 0, 1, 1, 1, 1];
+        let res = Ctx::huff_streams_serial(&src, &mut [0; 2], &table);
+        assert!(matches!(res, Err(Error::JumpTableError(_))));
+    }
+
+    fn jump_table_err(src: &[u8]) -> String {
+        match Ctx::jump_table(src) {
+            Err(Error::JumpTableError(msg)) => msg,
+            other => panic!("expected a jump table error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_jump_table_splits_streams() -> Result<(), Error> {
+        let src = [1, 0, 2, 0, 3, 0, 0xa, 0xb, 0xb, 0xc, 0xc, 0xc, 0xd];
+        let streams = Ctx::jump_table(&src)?;
+
+        assert_eq!(streams, [&[0xa][..], &[0xb, 0xb], &[0xc, 0xc, 0xc], &[0xd]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_jump_table_too_short() {
+        assert!(jump_table_err(&[1, 0, 1, 0, 1]).contains("too short"));
+    }
+
+    #[test]
+    fn test_jump_table_past_end() {
+        // The offsets fit in the whole source, but not once the 6 byte
+        // jump table itself is skipped.
+        let src = [3, 0, 2, 0, 2, 0, 0, 0, 0, 0, 0, 0];
+        assert!(jump_table_err(&src).contains("end at 7, past the 6 available bytes"));
+    }
+
+    #[test]
+    fn test_jump_table_degenerate_streams() {
+        // Nothing past the jump table: only all-empty or overrunning streams.
+        assert_eq!(jump_table_err(&[0; 6]), "stream 1 is empty");
+        assert_eq!(
+            jump_table_err(&[1, 0, 1, 0, 1, 0]),
+            "stream 1 would end at 1, past the 0 available bytes"
+        );
+
+        // A byte or two past it, for the first streams to take.
+        assert_eq!(
+            jump_table_err(&[1, 0, 1, 0, 1, 0, 0xa]),
+            "stream 2 would end at 2, past the 1 available bytes"
+        );
+        assert_eq!(
+            jump_table_err(&[1, 0, 0, 0, 1, 0, 0xa]),
+            "stream 2 is empty"
+        );
+        assert_eq!(
+            jump_table_err(&[1, 0, 1, 0, 1, 0, 0xa, 0xb]),
+            "stream 3 would end at 3, past the 2 available bytes"
+        );
+        assert_eq!(
+            jump_table_err(&[1, 0, 1, 0, 0, 0, 0xa, 0xb]),
+            "stream 3 is empty"
+        );
+        assert_eq!(
+            jump_table_err(&[1, 0, 1, 0, 1, 0, 0xa, 0xb, 0xc]),
+            "stream 4 is empty"
+        );
+    }
+
+    #[test]
+    fn test_jump_table_empty_streams() {
+        assert_eq!(
+            jump_table_err(&[0, 0, 1, 0, 1, 0, 1, 1, 1]),
+            "stream 1 is empty"
+        );
+        assert_eq!(
+            jump_table_err(&[1, 0, 0, 0, 1, 0, 1, 1, 1]),
+            "stream 2 is empty"
+        );
+        assert_eq!(
+            jump_table_err(&[1, 0, 1, 0, 0, 0, 1, 1, 1]),
+            "stream 3 is empty"
+        );
+        assert_eq!(
+            jump_table_err(&[1, 0, 1, 0, 1, 0, 1, 1, 1]),
+            "stream 4 is empty"
+        );
+    }
+
+    /// Huffman-codes `literals` as a 4-stream payload, jump table included.
+    fn four_streams(literals: &[u8]) -> (rzstd_huff0::DecodingTable, Vec<u8>) {
+        let mut histogram = [0u32; 256];
+        literals.iter().for_each(|&b| histogram[b as usize] += 1);
+        let encoding = rzstd_huff0::EncodingTable::from_histogram(&histogram)
+            .expect("literals have at least two distinct symbols");
+
+        let mut description = Vec::new();
+        encoding
+            .write_description(&mut description)
+            .expect("tree description fits");
+        let (decoding, _) = rzstd_huff0::DecodingTable::read(&description)
+            .expect("encoded description is valid");
+
+        let chunk = literals.len().div_ceil(4);
+        let streams = literals
+            .chunks(chunk)
+            .map(|segment| {
+                let mut stream = Vec::new();
+                encoding
+                    .encode(segment, &mut stream)
+                    .expect("symbols are in table");
+                stream
+            })
+            .collect::<Vec<_>>();
+
+        let mut src = Vec::new();
+        for stream in &streams[..3] {
+            src.extend((stream.len() as u16).to_le_bytes());
+        }
+        streams.iter().for_each(|s| src.extend(s));
+
+        (decoding, src)
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() -> Result<(), Error> {
+        let literals = (0..PARALLEL_LITERALS_THRESHOLD as u32 + 123)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 27) as u8)
+            .collect::<Vec<_>>();
+        let (table, src) = four_streams(&literals);
+
+        let mut parallel = vec![0; literals.len()];
+        Ctx::huff_streams_parallel(&src, &mut parallel, &table)?;
+
+        let mut serial = vec![0; literals.len()];
+        Ctx::huff_streams_serial(&src, &mut serial, &table)?;
+
+        assert_eq!(parallel, literals);
+        assert_eq!(serial, literals);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_reports_stream_errors() {
+        let literals = (0..PARALLEL_LITERALS_THRESHOLD as u32)
+            .map(|i| (i % 7) as u8)
+            .collect::<Vec<_>>();
+        let (table, mut src) = four_streams(&literals);
+
+        // Drops the sentinel of the last stream.
+        let last = src.len() - 1;
+        src[last] = 0;
+
+        let mut dst = vec![0; literals.len()];
+        assert!(matches!(
+            Ctx::huff_streams_parallel(&src, &mut dst, &table),
+            Err(Error::IO(rzstd_io::Error::MissingSentinelIn("stream 4")))
+        ));
+    }
+
+    #[test]
+    fn test_names_stream_missing_sentinel() {
+        let literals = (0..1000u32).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+        let (table, mut src) = four_streams(&literals);
+
+        // Drops the sentinel of the second stream.
+        let end = 6
+            + u16::from_le_bytes([src[0], src[1]]) as usize
+            + u16::from_le_bytes([src[2], src[3]]) as usize;
+        src[end - 1] = 0;
+
+        let mut dst = vec![0; literals.len()];
+        let err = Ctx::huff_streams_serial(&src, &mut dst, &table)
+            .expect_err("stream 2 has no sentinel");
+        assert_eq!(err.to_string(), "stream 2 is missing its end sentinel");
+
+        let mut dst = vec![0; literals.len()];
+        assert!(matches!(
+            Ctx::huff_streams_parallel(&src, &mut dst, &table),
+            Err(Error::IO(rzstd_io::Error::MissingSentinelIn("stream 2")))
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_jump_table(src in proptest::collection::vec(any::<u8>(), 0..64)) {
+            if let Ok(streams) = Ctx::jump_table(&src) {
+                prop_assert!(streams.iter().all(|s| !s.is_empty()));
+                prop_assert_eq!(
+                    streams.iter().map(|s| s.len()).sum::<usize>() + 6,
+                    src.len()
+                );
+            }
+        }
+
+        #[test]
+        fn fuzz_four_streams(
+            table in proptest::collection::vec(any::<u8>(), 6),
+            payload in proptest::collection::vec(any::<u8>(), 0..64),
+            regenerated in 0usize..256,
+        ) {
+            // Direct weights: one explicit weight of 1, the other inferred.
+            let (huff, _) = rzstd_huff0::DecodingTable::read(&[128 + 1, 0x10])
+                .expect("two equal weights form a valid tree");
+            let src = [table, payload].concat();
+            let mut dst = vec![0; regenerated];
+
+            // Only checks that adversarial input never panics.
+            let _ = Ctx::huff_streams(&src, &mut dst, &huff, Streams::Four);
+        }
+    }
+}
@@ -1,4 +1,7 @@
+pub use std::io::Read;
+
 pub use rzstd_foundation::*;
+pub use rzstd_io::ReadU8;
 
 pub use crate::errors::*;
 
@@ -11,15 +14,33 @@ pub enum TwoBitFlag {
 }
 
 impl TwoBitFlag {
-    /// It is expected at the caller site to truncate the input so it's < 4
-    pub fn from_u8(val: u8) -> TwoBitFlag {
-        assert!(val < 4, "Invalid value for TwoBitFlag");
+    /// The flag `val` holds, or `None` when it doesn't fit in two bits.
+    pub const fn try_from_u8(val: u8) -> Option<TwoBitFlag> {
         match val {
-            0 => TwoBitFlag::Zero,
-            1 => TwoBitFlag::One,
-            2 => TwoBitFlag::Two,
-            3 => TwoBitFlag::Three,
-            _ => unreachable!("Invalid value for TwoBitFlag"),
+            0 => Some(TwoBitFlag::Zero),
+            1 => Some(TwoBitFlag::One),
+            2 => Some(TwoBitFlag::Two),
+            3 => Some(TwoBitFlag::Three),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_from_u8() {
+        for val in 0..4 {
+            assert_eq!(
+                TwoBitFlag::try_from_u8(val).map(|flag| flag as u8),
+                Some(val)
+            );
+        }
+
+        for val in 4..=u8::MAX {
+            assert!(TwoBitFlag::try_from_u8(val).is_none());
         }
     }
 }
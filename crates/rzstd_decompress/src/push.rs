@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+
+use crate::{
+    MAGIC_NUM, block,
+    decoder::{Decoder, DecoderBuilder},
+    errors::Error,
+    frame,
+};
+
+/// Size of a frame's magic number, and of its checksum.
+const WORD_SIZE: usize = 4;
+
+/// Decodes input handed over in arbitrary pieces, as it arrives from a
+/// network connection or a callback, rather than pulled from a reader.
+///
+/// Bytes are buffered until the next frame header or whole block is
+/// available, which is then decoded as [Decoder] would. The decoder shouldn't
+/// be used any further once it returned an error.
+pub struct PushDecoder {
+    decoder: Decoder<'static, VecDeque<u8>>,
+    /// The frame whose blocks are being decoded, if any.
+    frame: Option<frame::Header>,
+    flushed: u64,
+    /// Decoded bytes, of which the first `taken` were already handed out.
+    out: Vec<u8>,
+    taken: usize,
+}
+
+impl Default for PushDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PushDecoder {
+    pub fn new() -> Self {
+        Self {
+            decoder: DecoderBuilder::new().source(VecDeque::new()).build(),
+            frame: None,
+            flushed: 0,
+            out: Vec::new(),
+            taken: 0,
+        }
+    }
+
+    /// Buffers `input`, then decodes everything it completes.
+    pub fn write(&mut self, input: &[u8]) -> Result<(), Error> {
+        self.decoder.source_mut().extend(input);
+        while self.step()? {}
+        Ok(())
+    }
+
+    /// The output decoded since the last call.
+    pub fn take_output(&mut self) -> &[u8] {
+        self.out.drain(..self.taken);
+        self.taken = self.out.len();
+        &self.out
+    }
+
+    /// Checks that the input ended between frames, failing with
+    /// [Error::TruncatedFrame] otherwise. Output not taken yet is dropped.
+    pub fn finish(mut self) -> Result<(), Error> {
+        let buffered = self.decoder.source_mut().len();
+        if self.frame.is_some() || buffered >= WORD_SIZE {
+            return Err(Error::TruncatedFrame);
+        }
+
+        if buffered > 0 && self.decoder.strict_trailing() {
+            return Err(Error::TrailingGarbage(buffered));
+        }
+        Ok(())
+    }
+
+    /// Decodes the next frame header, or block, if it is fully buffered, and
+    /// returns whether it was.
+    fn step(&mut self) -> Result<bool, Error> {
+        let Some(has_checksum) = self.frame.as_ref().map(frame::Header::has_checksum)
+        else {
+            let Some(needed) = self.header_size() else {
+                return Ok(false);
+            };
+            if self.decoder.source_mut().len() < needed {
+                return Ok(false);
+            }
+
+            self.frame = self.decoder.begin_frame()?;
+            self.flushed = self.decoder.frame_position();
+            return Ok(true);
+        };
+
+        let Some(block) = self.peek::<{ block::HEADER_SIZE }>() else {
+            return Ok(false);
+        };
+        let block = block::Header::read(&mut &block[..])?;
+        let mut needed = block::HEADER_SIZE + block.content_size() as usize;
+        if block.last_block() && has_checksum {
+            needed += WORD_SIZE;
+        }
+        if self.decoder.source_mut().len() < needed {
+            return Ok(false);
+        }
+
+        let out = &mut self.out;
+        let mut collect = |data: &[u8]| {
+            out.extend_from_slice(data);
+            Ok(())
+        };
+        let last =
+            self.decoder
+                .decode_block(&mut collect, &mut self.flushed, 1, &mut |_| {})?;
+
+        if last && let Some(header) = self.frame.take() {
+            self.decoder.end_frame(&header)?;
+        }
+        Ok(true)
+    }
+
+    /// The size of the magic number and frame header the buffered input
+    /// starts with, once enough of it is there to tell.
+    ///
+    /// Anything but a zstd magic number only takes its own 4 bytes, which
+    /// [Decoder] then rejects.
+    fn header_size(&mut self) -> Option<usize> {
+        let Some(head) = self.peek::<{ WORD_SIZE + 1 }>() else {
+            let magic = self.peek::<WORD_SIZE>()?;
+            return (u32::from_le_bytes(magic) != MAGIC_NUM).then_some(WORD_SIZE);
+        };
+
+        let magic = u32::from_le_bytes([head[0], head[1], head[2], head[3]]);
+        if magic != MAGIC_NUM {
+            return Some(WORD_SIZE);
+        }
+        // A descriptor that can't be laid out is left to Decoder to reject.
+        let size = frame::Header::size_for_descriptor(head[WORD_SIZE]).unwrap_or(1);
+        Some(WORD_SIZE + size)
+    }
+
+    /// The first `N` buffered bytes, without consuming them.
+    fn peek<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let buf = self.decoder.source_mut();
+        if buf.len() < N {
+            return None;
+        }
+
+        let mut head = [0; N];
+        head.iter_mut().zip(buf.iter()).for_each(|(h, &b)| *h = b);
+        Some(head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_in_pieces(src: &[u8], piece: usize) -> Result<Vec<u8>, Error> {
+        let mut decoder = PushDecoder::new();
+        let mut out = Vec::new();
+        for input in src.chunks(piece) {
+            decoder.write(input)?;
+            out.extend_from_slice(decoder.take_output());
+        }
+        decoder.finish()?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_byte_at_a_time() -> Result<(), Error> {
+        let content = (0..300_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 29) as u8)
+            .collect::<Vec<_>>();
+        let checksummed = {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.include_checksum(true)?;
+            std::io::Write::write_all(&mut encoder, &content[..1000])?;
+            encoder.finish()?
+        };
+        let src = [zstd::encode_all(&content[..], 3)?, checksummed].concat();
+
+        let mut expected = Vec::new();
+        Decoder::new_auto(&src[..]).decode(&mut expected)?;
+
+        assert_eq!(push_in_pieces(&src, 1)?, expected);
+        assert_eq!(push_in_pieces(&src, 777)?, expected);
+        assert_eq!(push_in_pieces(&src, src.len())?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_finish_mid_frame() -> Result<(), Error> {
+        let src = zstd::encode_all(&b"unfinished".repeat(100)[..], 3)?;
+
+        for len in [5, src.len() - 1] {
+            let mut decoder = PushDecoder::new();
+            decoder.write(&src[..len])?;
+            assert!(matches!(decoder.finish(), Err(Error::TruncatedFrame)));
+        }
+
+        let mut decoder = PushDecoder::new();
+        assert!(matches!(
+            decoder.write(&MAGIC_NUM.to_be_bytes()),
+            Err(Error::InvalidMagicNum(_))
+        ));
+        Ok(())
+    }
+}
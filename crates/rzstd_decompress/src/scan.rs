@@ -0,0 +1,278 @@
+use std::ops::Range;
+
+use rzstd_io::ReadU32;
+
+use crate::{
+    MAGIC_NUM, MAX_BLOCK_SIZE, SKIPPABLE_MAGIC_NUM_RANGE, block, decompress, frame,
+    prelude::*,
+};
+
+const CHECKSUM_SIZE: usize = 4;
+
+/// Returns the byte range of every frame in `src`, skippable frames included.
+///
+/// Only magic numbers, frame headers and block headers are parsed; block
+/// contents are skipped over using their declared sizes, so this is much
+/// cheaper than decoding.
+pub fn frame_boundaries(src: &[u8]) -> Result<Vec<Range<usize>>, Error> {
+    let mut frames = Vec::new();
+
+    let mut start = 0;
+    while start < src.len() {
+        let mut r = &src[start..];
+        skip_frame(&mut r, |_, _| Ok(()))?;
+
+        let end = src.len() - r.len();
+        frames.push(start..end);
+        start = end;
+    }
+
+    Ok(frames)
+}
+
+/// Checks that `src` is made of well-formed frames without decoding them:
+/// every frame header is valid, every block header declares a size its frame
+/// allows, and every flagged checksum is there. Returns the first problem
+/// found.
+///
+/// Like [frame_boundaries], block contents are skipped over, so this is much
+/// cheaper than decoding, and passing it doesn't mean decoding will succeed.
+pub fn check_structure(src: &[u8]) -> Result<(), Error> {
+    let mut r = src;
+    while !r.is_empty() {
+        // Output of the frame's raw and RLE blocks, the only ones whose
+        // size is known without decoding.
+        let mut regenerated = 0u64;
+
+        skip_frame(&mut r, |frame, block| {
+            let max_block_size = frame.window_size()?.min(MAX_BLOCK_SIZE as u64);
+            let size = block.decompressed_size().unwrap_or(block.content_size());
+            if size as u64 > max_block_size {
+                return Err(Error::BlockSizeOutOfBounds(size));
+            }
+
+            // Even an empty compressed block holds a literals section header
+            // and a sequences section header.
+            if matches!(block.block_type(), block::Type::Compressed)
+                && block.content_size() < 2
+            {
+                return Err(Error::Corruption);
+            }
+
+            regenerated += block.decompressed_size().unwrap_or(0) as u64;
+            if frame.content_size().is_some_and(|size| regenerated > size) {
+                return Err(Error::Corruption);
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Decodes only the frame at `frame_index` in `src`, as counted by
+/// [frame_boundaries], skippable frames included.
+///
+/// A skippable frame holds no compressed data, so it decodes to nothing.
+pub fn decode_frame_at(src: &[u8], frame_index: usize) -> Result<Vec<u8>, Error> {
+    let frames = frame_boundaries(src)?;
+    let frame = frames
+        .get(frame_index)
+        .ok_or(Error::FrameIndexOutOfRange(frame_index))?;
+
+    let src = &src[frame.clone()];
+    let magic_num = (&src[..]).read_u32()?;
+    if SKIPPABLE_MAGIC_NUM_RANGE.contains(&magic_num) {
+        return Ok(Vec::new());
+    }
+
+    decompress(src)
+}
+
+/// Returns the size of the window buffer [crate::Decoder::new] needs to decode
+/// every frame in `src`: the largest window any of them declares, plus
+/// [MAX_BLOCK_SIZE]. Input without any zstd frame needs none.
+///
+/// Like [frame_boundaries], this only parses headers.
+pub fn required_window_buffer(src: &[u8]) -> Result<usize, Error> {
+    let mut required = 0;
+
+    for frame in frame_boundaries(src)? {
+        let mut r = &src[frame];
+        if SKIPPABLE_MAGIC_NUM_RANGE.contains(&r.read_u32()?) {
+            continue;
+        }
+
+        let window_size = frame::Header::read(&mut r)?.window_size()? as usize;
+        required = required.max(window_size + MAX_BLOCK_SIZE as usize);
+    }
+
+    Ok(required)
+}
+
+/// Advances `r` past the frame it starts with, handing every block header to
+/// `on_block` along with the frame's.
+fn skip_frame(
+    r: &mut &[u8],
+    mut on_block: impl FnMut(&frame::Header, &block::Header) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let magic_num = r.read_u32()?;
+
+    if SKIPPABLE_MAGIC_NUM_RANGE.contains(&magic_num) {
+        let size = r.read_u32()? as usize;
+        return skip(r, size);
+    }
+
+    if magic_num != MAGIC_NUM {
+        return Err(Error::InvalidMagicNum(magic_num));
+    }
+
+    let header = frame::Header::read(r)?;
+    loop {
+        let block = block::Header::read(r)?;
+        on_block(&header, &block)?;
+        skip(r, block.content_size() as usize)?;
+
+        if block.last_block() {
+            break;
+        }
+    }
+
+    if header.has_checksum() {
+        skip(r, CHECKSUM_SIZE)?;
+    }
+
+    Ok(())
+}
+
+fn skip(r: &mut &[u8], n: usize) -> Result<(), Error> {
+    let Some(rest) = r.get(n..) else {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    };
+
+    *r = rest;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skippable_frame(data: &[u8]) -> Vec<u8> {
+        let mut frame = SKIPPABLE_MAGIC_NUM_RANGE.start().to_le_bytes().to_vec();
+        frame.extend((data.len() as u32).to_le_bytes());
+        frame.extend(data);
+        frame
+    }
+
+    #[test]
+    fn test_frame_boundaries() -> Result<(), Error> {
+        let first = zstd::encode_all(&b"first frame ".repeat(100)[..], 3)?;
+        let skippable = skippable_frame(b"user data");
+        let second = {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.include_checksum(true)?;
+            std::io::Write::write_all(&mut encoder, &b"second frame".repeat(50_000))?;
+            encoder.finish()?
+        };
+
+        let src = [first.as_slice(), &skippable, &second].concat();
+
+        let a = first.len();
+        let b = a + skippable.len();
+        let c = b + second.len();
+        assert_eq!(frame_boundaries(&src)?, [0..a, a..b, b..c]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_frame_at() -> Result<(), Error> {
+        let first = zstd::encode_all(&b"first frame ".repeat(100)[..], 3)?;
+        let second = zstd::encode_all(&b"second frame".repeat(100)[..], 3)?;
+        let src = [first.as_slice(), &skippable_frame(b"user data"), &second].concat();
+
+        assert_eq!(decode_frame_at(&src, 0)?, b"first frame ".repeat(100));
+        assert_eq!(decode_frame_at(&src, 1)?, b"");
+        assert_eq!(decode_frame_at(&src, 2)?, b"second frame".repeat(100));
+        assert!(matches!(
+            decode_frame_at(&src, 3),
+            Err(Error::FrameIndexOutOfRange(3))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_required_window_buffer() -> Result<(), Error> {
+        let frame = |data: &[u8], window_log: u32| -> Result<Vec<u8>, Error> {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.include_contentsize(false)?;
+            encoder.window_log(window_log)?;
+            std::io::Write::write_all(&mut encoder, data)?;
+            Ok(encoder.finish()?)
+        };
+
+        let data = b"window".repeat(100_000);
+        let small = frame(&data, 12)?;
+        let large = frame(&data, 17)?;
+        let src = [small.as_slice(), &large, &small].concat();
+
+        let required = required_window_buffer(&src)?;
+        assert_eq!(required, (1 << 17) + MAX_BLOCK_SIZE as usize);
+        let with_skippable = [skippable_frame(b"user data"), src.clone()].concat();
+        assert_eq!(required_window_buffer(&with_skippable)?, required);
+
+        let mut buf = vec![0; required];
+        let mut out = Vec::new();
+        crate::Decoder::new(&src[..], &mut buf, required).decode(&mut out)?;
+        assert_eq!(out, data.repeat(3));
+
+        assert_eq!(required_window_buffer(&skippable_frame(b"data"))?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_structure() -> Result<(), Error> {
+        let first = zstd::encode_all(&b"first frame ".repeat(100)[..], 3)?;
+        let second = {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3)?;
+            encoder.include_checksum(true)?;
+            std::io::Write::write_all(&mut encoder, &b"second frame".repeat(50_000))?;
+            encoder.finish()?
+        };
+        let src = [first.as_slice(), &skippable_frame(b"user data"), &second].concat();
+        check_structure(&src)?;
+        check_structure(&[])?;
+
+        // Without its checksum.
+        assert!(check_structure(&second[..second.len() - 4]).is_err());
+
+        // A 1 KiB window, and a last raw block twice as large.
+        let mut frame = MAGIC_NUM.to_le_bytes().to_vec();
+        frame.extend([0x00, 0x00]);
+        frame.extend(((2048 << 3) | 1u32).to_le_bytes()[..3].iter());
+        frame.extend([0xAB; 2048]);
+        assert!(matches!(
+            check_structure(&frame),
+            Err(Error::BlockSizeOutOfBounds(2048))
+        ));
+
+        // The same block declaring more than it holds.
+        frame[6..9].copy_from_slice(&((1024 << 3) | 1u32).to_le_bytes()[..3]);
+        check_structure(&frame[..6 + 3 + 1024])?;
+        assert!(check_structure(&frame[..6 + 3 + 1000]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_frame_boundaries_truncated() -> Result<(), Error> {
+        let frame = zstd::encode_all(&b"truncated".repeat(100)[..], 3)?;
+
+        assert!(frame_boundaries(&frame[..frame.len() - 1]).is_err());
+        assert!(frame_boundaries(&skippable_frame(b"data")[..10]).is_err());
+        assert!(frame_boundaries(&[])?.is_empty());
+
+        Ok(())
+    }
+}
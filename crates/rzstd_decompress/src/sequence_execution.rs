@@ -1,44 +1,63 @@
-use crate::{context::Context, prelude::*};
+use crate::{MAX_BLOCK_SIZE, context::Context, prelude::*, sequences_section::Sequence};
 
 impl<R: rzstd_io::Reader> Context<'_, R> {
-    pub fn execute_sequences(&mut self) -> Result<(), Error> {
+    /// Applies the block's sequences, whose offsets are already resolved, to
+    /// the window, first handing each one to `on_seq`.
+    ///
+    /// A block regenerates at most the smaller of the window size and
+    /// [MAX_BLOCK_SIZE] bytes; sequences producing more than that are
+    /// rejected before they reach the window. Once the window reaches
+    /// [Context::output_cap], the remaining sequences and literals are
+    /// skipped.
+    pub fn execute_sequences(
+        &mut self,
+        on_seq: &mut impl FnMut(&Sequence),
+    ) -> Result<(), Error> {
         tracing::debug!("\nsequence execution \n");
 
         let literals = &self.literals_buf[..self.literals_idx];
         let sequences = &self.sequences_buf[..self.sequences_idx];
-        let offset_hist = &mut self.offset_hist;
+        let max_produced = self.window_buf.size().min(MAX_BLOCK_SIZE as usize);
+
+        // Validated once for the whole block, so that the loop below can slice
+        // the literals without checking each sequence.
+        let (lit_total, match_total) =
+            sequences.iter().fold((0u64, 0u64), |(lits, matches), seq| {
+                (lits + seq.lit_len as u64, matches + seq.match_len as u64)
+            });
+        if lit_total > literals.len() as u64 {
+            return Err(literals_overread(sequences, literals.len()));
+        }
+        if literals.len() as u64 + match_total > max_produced as u64 {
+            return Err(Error::Corruption);
+        }
 
         let mut lit_idx = 0usize;
         let mut literal: &[u8];
 
         for seq in sequences {
+            if self.window_buf.position() >= self.output_cap {
+                self.literals_idx = 0;
+                return Ok(());
+            }
+
             let lit_len = seq.lit_len as usize;
+            let offset = seq.offset as usize;
+            on_seq(seq);
+
             if lit_len > 0 {
-                let next_lit_idx = lit_idx.checked_add(lit_len).ok_or(
-                    Error::LiteralsBufferOverread {
-                        idx: lit_idx,
-                        len: lit_len,
-                    },
-                )?;
-                if next_lit_idx > literals.len() {
-                    return Err(Error::LiteralsBufferOverread {
-                        idx: lit_idx,
-                        len: lit_len,
-                    });
-                }
-
-                literal = &literals[lit_idx..next_lit_idx];
-                self.window_buf.push_buf(literal);
+                debug_assert!(lit_idx + lit_len <= literals.len());
+                // SAFETY: the literal lengths of all sequences sum to at most
+                // `literals.len()`, as checked before the loop.
+                literal = unsafe { literals.get_unchecked(lit_idx..lit_idx + lit_len) };
+                self.window_buf.push_buf(literal)?;
                 lit_idx += lit_len;
             } else {
                 literal = &[];
             }
 
-            let offset = update_offset_hist(offset_hist, seq.offset, lit_len)?;
-
             let match_len = seq.match_len as usize;
 
-            tracing::debug!("offset_hist={:?}", offset_hist);
             tracing::debug!(
                 "lit={:?}; offset={}, match={:?}",
                 literal,
@@ -51,8 +70,8 @@ impl<R: rzstd_io::Reader> Context<'_, R> {
             }
         }
 
-        if lit_idx < literals.len() {
-            self.window_buf.push_buf(&literals[lit_idx..]);
+        if lit_idx < literals.len() && self.window_buf.position() < self.output_cap {
+            self.window_buf.push_buf(&literals[lit_idx..])?;
         }
         tracing::debug!(
             "lit_remainder.len={:?}, lit_remainder={:?}",
@@ -64,64 +83,86 @@ impl<R: rzstd_io::Reader> Context<'_, R> {
     }
 }
 
-fn update_offset_hist(
-    history: &mut [usize; 3],
-    offset: u32,
-    lit_len: usize,
-) -> Result<usize, Error> {
-    let next_offset = if lit_len > 0 {
-        match offset {
-            1..=3 => history[offset as usize - 1],
-            _ => {
-                //new offset
-                offset as usize - 3
-            }
-        }
-    } else {
-        match offset {
-            1..=2 => history[offset as usize],
-            3 => history[0] - 1,
-            _ => {
-                //new offset
-                offset as usize - 3
-            }
+/// Builds the error for the first sequence whose literals run past the end of
+/// `literals_len`.
+#[cold]
+fn literals_overread(sequences: &[Sequence], literals_len: usize) -> Error {
+    let mut idx = 0usize;
+    for seq in sequences {
+        let len = seq.lit_len as usize;
+        if idx + len > literals_len {
+            return Error::LiteralsBufferOverread { idx, len };
         }
-    };
+        idx += len;
+    }
 
-    //update history
-    if lit_len > 0 {
-        match offset {
-            1 => {
-                //nothing
-            }
-            2 => {
-                history[1] = history[0];
-                history[0] = next_offset;
-            }
-            _ => {
-                history[2] = history[1];
-                history[1] = history[0];
-                history[0] = next_offset;
-            }
-        }
-    } else {
-        match offset {
-            1 => {
-                history[1] = history[0];
-                history[0] = next_offset;
-            }
-            2 => {
-                history[2] = history[1];
-                history[1] = history[0];
-                history[0] = next_offset;
+    unreachable!("the literal lengths were checked to overrun the buffer")
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Executes the sequences one byte at a time, checking every access.
+    fn execute_checked(literals: &[u8], sequences: &[Sequence]) -> Option<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut lit_idx = 0;
+
+        for seq in sequences {
+            let lit_len = seq.lit_len as usize;
+            let offset = seq.offset as usize;
+
+            out.extend(literals.get(lit_idx..lit_idx + lit_len)?);
+            lit_idx += lit_len;
+
+            if seq.match_len > 0 && (offset == 0 || offset > out.len()) {
+                return None;
             }
-            _ => {
-                history[2] = history[1];
-                history[1] = history[0];
-                history[0] = next_offset;
+            for _ in 0..seq.match_len {
+                out.push(out[out.len() - offset]);
             }
         }
+
+        out.extend(&literals[lit_idx..]);
+        Some(out)
+    }
+
+    fn execute(literals: &[u8], sequences: &[Sequence]) -> Option<Vec<u8>> {
+        const WINDOW_SIZE: usize = 1 << 20;
+
+        let mut buf = vec![0; WINDOW_SIZE + MAX_BLOCK_SIZE as usize];
+        let mut ctx = Context::new(&[][..], &mut buf, WINDOW_SIZE);
+        ctx.reset(WINDOW_SIZE).ok()?;
+
+        ctx.literals_buf[..literals.len()].copy_from_slice(literals);
+        ctx.literals_idx = literals.len();
+        ctx.sequences_buf[..sequences.len()].copy_from_slice(sequences);
+        ctx.sequences_idx = sequences.len();
+
+        ctx.execute_sequences(&mut |_| {}).ok()?;
+        Some(ctx.window_buf.as_slice()[..ctx.window_buf.index()].to_vec())
     }
 
-    Ok(next_offset)
+    fn sequence() -> impl Strategy<Value = Sequence> {
+        (0..16u32, 1..24u32, 0..32u32).prop_map(|(lit_len, offset, match_len)| Sequence {
+            lit_len,
+            offset,
+            match_len,
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_checked_execution(
+            literals in prop::collection::vec(any::<u8>(), 0..128),
+            sequences in prop::collection::vec(sequence(), 0..16),
+        ) {
+            prop_assert_eq!(
+                execute(&literals, &sequences),
+                execute_checked(&literals, &sequences)
+            );
+        }
+    }
 }
@@ -2,7 +2,9 @@ use crate::{
     DefaultDistribution, LL_DIST, ML_DIST, OF_DIST, context::Context, prelude::*,
 };
 
-#[derive(Clone, Copy, Default)]
+/// A run of literals followed by a match, as decoded from a block's
+/// sequences section.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
 pub struct Sequence {
     pub lit_len: u32,
     pub offset: u32,
@@ -20,14 +22,26 @@ impl std::fmt::Debug for Sequence {
 }
 
 impl<R: rzstd_io::Reader> Context<'_, R> {
-    pub fn sequence_section(&mut self, seq_size: usize) -> Result<(), Error> {
+    pub fn sequence_section(
+        &mut self,
+        seq_size: usize,
+        on_seq: &mut impl FnMut(&Sequence),
+    ) -> Result<(), Error> {
         let scratch = &mut self.scratch_buf[..seq_size];
         self.src.read_exact(scratch)?;
         let mut reader: &[u8] = scratch;
 
         let header = Header::read(&mut reader)?;
         if header.n_seqs == 0 {
-            return Ok(());
+            // The block is made of literals only.
+            self.sequences_idx = 0;
+            return self.execute_sequences(on_seq);
+        }
+        if header.n_seqs as usize > self.sequences_buf.len() {
+            return Err(Error::TooManySequences(header.n_seqs));
+        }
+        if header.n_seqs as usize > self.max_sequences {
+            return Err(Error::Corruption);
         }
 
         tracing::debug!("\nsequence section header={:?}\n", header);
@@ -36,38 +50,32 @@ impl<R: rzstd_io::Reader> Context<'_, R> {
 
         let mut idx = 0;
 
-        tracing::debug!("updating ll mode={:?}", modes.literal_lengths());
-        idx += update_table(
-            modes.literal_lengths(),
-            LL_DIST,
-            &reader[idx..],
-            &mut self.fse.ll,
-        )?;
+        let ll_mode = modes.literal_lengths()?;
+        tracing::debug!("updating ll mode={:?}", ll_mode);
+        let max_log = self.max_fse_accuracy_log;
+        idx += update_table(ll_mode, LL_DIST, &reader[idx..], &mut self.fse.ll, max_log)?;
         tracing::debug!(
             "ll_table.len={:?}; ll_table={:?}",
-            self.fse.ll.as_ref().unwrap().table().len(),
-            self.fse.ll.as_ref().unwrap().table(),
+            self.fse.ll.as_ref().map(|t| t.table().len()),
+            self.fse.ll.as_ref().map(|t| t.table()),
         );
 
-        tracing::debug!("\nupdating of mode={:?}", modes.offsets());
-        idx += update_table(modes.offsets(), OF_DIST, &reader[idx..], &mut self.fse.of)?;
+        let of_mode = modes.offsets()?;
+        tracing::debug!("\nupdating of mode={:?}", of_mode);
+        idx += update_table(of_mode, OF_DIST, &reader[idx..], &mut self.fse.of, max_log)?;
         tracing::debug!(
             "of_table.len={:?}; of_table={:?}",
-            self.fse.of.as_ref().unwrap().table().len(),
-            self.fse.of.as_ref().unwrap().table(),
+            self.fse.of.as_ref().map(|t| t.table().len()),
+            self.fse.of.as_ref().map(|t| t.table()),
         );
 
-        tracing::debug!("\nupdating ml mode={:?}", modes.match_lengths());
-        idx += update_table(
-            modes.match_lengths(),
-            ML_DIST,
-            &reader[idx..],
-            &mut self.fse.ml,
-        )?;
+        let ml_mode = modes.match_lengths()?;
+        tracing::debug!("\nupdating ml mode={:?}", ml_mode);
+        idx += update_table(ml_mode, ML_DIST, &reader[idx..], &mut self.fse.ml, max_log)?;
         tracing::debug!(
             "ml_table.len={:?}; ml_table={:?}\n",
-            self.fse.ml.as_ref().unwrap().table().len(),
-            self.fse.ml.as_ref().unwrap().table(),
+            self.fse.ml.as_ref().map(|t| t.table().len()),
+            self.fse.ml.as_ref().map(|t| t.table()),
         );
 
         let mut r = rzstd_io::ReverseBitReader::new(&reader[idx..])?;
@@ -76,49 +84,25 @@ impl<R: rzstd_io::Reader> Context<'_, R> {
         let of_table = self.fse.of.as_ref().ok_or(Error::MissingSeqTable)?;
         let ml_table = self.fse.ml.as_ref().ok_or(Error::MissingSeqTable)?;
 
-        let mut ll_dec = rzstd_fse::Decoder::new(ll_table, &mut r)?;
-        let mut of_dec = rzstd_fse::Decoder::new(of_table, &mut r)?;
-        let mut ml_dec = rzstd_fse::Decoder::new(ml_table, &mut r)?;
+        let mut decoder =
+            SequenceDecoder::new(ll_table, of_table, ml_table, self.offset_hist, &mut r)?;
 
+        // Once the sequences decoded so far reach the output cap, the rest of
+        // them would be skipped anyway, so they're left in the bitstream.
+        let mut produced = self.window_buf.position();
         self.sequences_idx = header.n_seqs as usize;
-        let dst = &mut self.sequences_buf[..self.sequences_idx];
-        let mut dst_idx = 0;
-
-        let mut ll = ll_dec.peek();
-        let mut of = of_dec.peek();
-        let mut ml = ml_dec.peek();
-
-        let offset = decode_of(of, &mut r)?;
-        let match_len = decode_ml(ml, &mut r)?;
-        let lit_len = decode_ll(ll, &mut r)?;
-
-        dst[dst_idx] = Sequence {
-            lit_len,
-            match_len,
-            offset,
-        };
-        dst_idx += 1;
-
-        for _ in 1..header.n_seqs {
-            ll_dec.update(&mut r)?;
-            ml_dec.update(&mut r)?;
-            of_dec.update(&mut r)?;
-
-            ll = ll_dec.peek();
-            of = of_dec.peek();
-            ml = ml_dec.peek();
-
-            let offset = decode_of(of, &mut r)?;
-            let match_len = decode_ml(ml, &mut r)?;
-            let lit_len = decode_ll(ll, &mut r)?;
-
-            dst[dst_idx] = Sequence {
-                lit_len,
-                match_len,
-                offset,
-            };
-            dst_idx += 1;
+        for (i, seq) in self.sequences_buf[..self.sequences_idx]
+            .iter_mut()
+            .enumerate()
+        {
+            *seq = decoder.next(&mut r)?;
+            produced += seq.lit_len as u64 + seq.match_len as u64;
+            if produced >= self.output_cap {
+                self.sequences_idx = i + 1;
+                break;
+            }
         }
+        self.offset_hist = decoder.offsets();
 
         tracing::debug!(
             "seqs.len={:?}; seqs={:?}",
@@ -126,11 +110,150 @@ impl<R: rzstd_io::Reader> Context<'_, R> {
             self.sequences_buf
         );
 
-        if r.bits_remaining() > 0 {
+        if self.sequences_idx == header.n_seqs as usize && r.bits_remaining() > 0 {
             return Err(Error::ExtraBitsInStream(r.bits_remaining()));
         }
 
-        self.execute_sequences()
+        self.execute_sequences(on_seq)
+    }
+}
+
+/// Decodes a block's sequences one at a time, keeping the literal length,
+/// offset and match length states in step and resolving each offset against
+/// the repeat offset history.
+pub struct SequenceDecoder<'t> {
+    ll: rzstd_fse::Decoder<'t, { LL_DIST.table_size() }>,
+    of: rzstd_fse::Decoder<'t, { OF_DIST.table_size() }>,
+    ml: rzstd_fse::Decoder<'t, { ML_DIST.table_size() }>,
+    offsets: RepeatOffsets,
+    /// Whether the states still point at the first sequence, which is decoded
+    /// before any of them is updated.
+    first: bool,
+}
+
+impl<'t> SequenceDecoder<'t> {
+    /// Reads the initial states, in the order the format lays them out.
+    pub fn new(
+        ll_table: &'t rzstd_fse::DecodingTable<{ LL_DIST.table_size() }>,
+        of_table: &'t rzstd_fse::DecodingTable<{ OF_DIST.table_size() }>,
+        ml_table: &'t rzstd_fse::DecodingTable<{ ML_DIST.table_size() }>,
+        offsets: RepeatOffsets,
+        r: &mut rzstd_io::ReverseBitReader,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            ll: rzstd_fse::Decoder::new(ll_table, r)?,
+            of: rzstd_fse::Decoder::new(of_table, r)?,
+            ml: rzstd_fse::Decoder::new(ml_table, r)?,
+            offsets,
+            first: true,
+        })
+    }
+
+    /// Decodes the next sequence, with its offset resolved.
+    ///
+    /// Every sequence but the first starts by updating the states, so the
+    /// bits of the last one's updates are never read.
+    pub fn next(
+        &mut self,
+        r: &mut rzstd_io::ReverseBitReader,
+    ) -> Result<Sequence, Error> {
+        if !self.first {
+            self.ll.update(r)?;
+            self.ml.update(r)?;
+            self.of.update(r)?;
+        }
+        self.first = false;
+
+        let offset = decode_of(self.of.peek(), r)?;
+        let match_len = decode_ml(self.ml.peek(), r)?;
+        let lit_len = decode_ll(self.ll.peek(), r)?;
+        let offset = self.offsets.resolve(offset, lit_len as usize)?;
+
+        Ok(Sequence {
+            lit_len,
+            offset: offset as u32,
+            match_len,
+        })
+    }
+
+    /// The repeat offset history, as left by the sequences decoded so far.
+    pub fn offsets(&self) -> RepeatOffsets {
+        self.offsets
+    }
+}
+
+/// The three most recent offsets, which sequences can refer back to instead
+/// of spelling out a new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepeatOffsets(pub [usize; 3]);
+
+impl RepeatOffsets {
+    /// Turns an offset value as decoded from the stream into the actual
+    /// offset, updating the history.
+    ///
+    /// Values 1 to 3 pick a repeat offset, shifted by one when the sequence
+    /// has no literals; anything above is a new offset, 3 more than the
+    /// actual one. A value resolving to offset 0 is corrupt.
+    pub fn resolve(&mut self, offset: u32, lit_len: usize) -> Result<usize, Error> {
+        let history = &mut self.0;
+        let next_offset = if lit_len > 0 {
+            match offset {
+                1..=3 => history[offset as usize - 1],
+                _ => {
+                    //new offset
+                    offset as usize - 3
+                }
+            }
+        } else {
+            match offset {
+                1..=2 => history[offset as usize],
+                3 => history[0].checked_sub(1).ok_or(Error::Corruption)?,
+                _ => {
+                    //new offset
+                    offset as usize - 3
+                }
+            }
+        };
+        if next_offset == 0 {
+            return Err(Error::Corruption);
+        }
+
+        //update history
+        if lit_len > 0 {
+            match offset {
+                1 => {
+                    //nothing
+                }
+                2 => {
+                    history[1] = history[0];
+                    history[0] = next_offset;
+                }
+                _ => {
+                    history[2] = history[1];
+                    history[1] = history[0];
+                    history[0] = next_offset;
+                }
+            }
+        } else {
+            match offset {
+                1 => {
+                    history[1] = history[0];
+                    history[0] = next_offset;
+                }
+                2 => {
+                    history[2] = history[1];
+                    history[1] = history[0];
+                    history[0] = next_offset;
+                }
+                _ => {
+                    history[2] = history[1];
+                    history[1] = history[0];
+                    history[0] = next_offset;
+                }
+            }
+        }
+
+        Ok(next_offset)
     }
 }
 
@@ -194,16 +317,21 @@ impl CompressionModes {
         Ok(ret)
     }
 
-    fn literal_lengths(&self) -> Mode {
-        TwoBitFlag::from_u8((self.0 >> 6) & 0x3).into()
+    fn literal_lengths(&self) -> Result<Mode, Error> {
+        Self::mode(self.0 >> 6)
     }
 
-    fn offsets(&self) -> Mode {
-        TwoBitFlag::from_u8((self.0 >> 4) & 0x3).into()
+    fn offsets(&self) -> Result<Mode, Error> {
+        Self::mode(self.0 >> 4)
     }
 
-    fn match_lengths(&self) -> Mode {
-        TwoBitFlag::from_u8((self.0 >> 2) & 0x3).into()
+    fn match_lengths(&self) -> Result<Mode, Error> {
+        Self::mode(self.0 >> 2)
+    }
+
+    fn mode(bits: u8) -> Result<Mode, Error> {
+        let flag = TwoBitFlag::try_from_u8(bits & 0x3).ok_or(Error::Corruption)?;
+        Ok(flag.into())
     }
 
     fn reserved(&self) -> u8 {
@@ -212,6 +340,7 @@ impl CompressionModes {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Mode {
     /// A predefined FSE distribution table is used. No distribution table will
     /// be present.
@@ -243,12 +372,21 @@ impl From<TwoBitFlag> for Mode {
     }
 }
 
-fn update_table<const N: usize>(
+/// Tables read from the stream are rejected above the distribution's own
+/// accuracy log or `max_accuracy_log`, whichever is lower, and when they
+/// declare more symbols than the symbol type has codes.
+pub(crate) fn update_table<const N: usize>(
     mode: Mode,
     dist: DefaultDistribution,
     src: &[u8],
     curr: &mut Option<rzstd_fse::DecodingTable<N>>,
+    max_accuracy_log: u8,
 ) -> Result<usize, Error> {
+    // Nothing but the callers ties `N` to the distribution.
+    if N != dist.table_size() {
+        return Err(Error::Corruption);
+    }
+
     match mode {
         Mode::Repeat => {
             if curr.is_none() {
@@ -257,24 +395,26 @@ fn update_table<const N: usize>(
             Ok(0)
         }
         Mode::Predefined => {
-            let mut norm = rzstd_fse::NormalizedDistribution::from_predefined(
-                dist.predefined_table(),
-                dist.accuracy_log() as u8,
-            )?;
-            *curr = Some(rzstd_fse::DecodingTable::from_distribution(&mut norm)?);
+            *curr = Some(dist.build_table()?);
             Ok(0)
         }
         Mode::RLE => {
-            let sym = *src.get(0).ok_or(Error::EmptyRLESource)?;
+            let sym = *src.first().ok_or(Error::EmptyRLESource)?;
             *curr = Some(rzstd_fse::DecodingTable::rle(sym));
 
             Ok(1)
         }
         Mode::FSECompressed => {
             let mut br = rzstd_io::BitReader::new(src)?;
-            *curr = Some(rzstd_fse::DecodingTable::read(&mut br, dist.table_size())?);
+            let (table, consumed) = rzstd_fse::DecodingTable::read_with_max_symbols(
+                &mut br,
+                src.len(),
+                (dist.accuracy_log() as u8).min(max_accuracy_log),
+                dist.max_symbols(),
+            )?;
+            *curr = Some(table);
 
-            Ok(br.bytes_consumed())
+            Ok(consumed)
         }
     }
 }
@@ -399,3 +539,210 @@ fn decode_of(code: u8, r: &mut rzstd_io::ReverseBitReader) -> Result<u32, Error>
     let extra = r.read(code)?;
     Ok((1u32 << (code & 0x1F)) + extra as u32)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_BLOCK_SIZE;
+
+    #[test]
+    fn test_extended_sequence_count() -> Result<(), Error> {
+        let header = Header::read(&mut &[255, 0xFF, 0xFF, 0x00][..])?;
+        assert_eq!(header.n_seqs, 0xFFFF + 0x7F00);
+        Ok(())
+    }
+
+    #[test]
+    fn test_too_many_sequences() {
+        // The largest count the 3-byte form can express, with predefined
+        // tables for everything.
+        let src = [255, 0xFF, 0xFF, 0x00, 0x01];
+        let mut buf = vec![0; 2 * MAX_BLOCK_SIZE as usize];
+        let mut ctx = Context::new(&src[..], &mut buf, MAX_BLOCK_SIZE as usize);
+        ctx.sequences_buf.truncate(1000);
+
+        assert!(matches!(
+            ctx.sequence_section(src.len(), &mut |_| {}),
+            Err(Error::TooManySequences(n)) if n == 0xFFFF + 0x7F00
+        ));
+    }
+
+    #[test]
+    fn test_sequence_decoder() -> Result<(), Error> {
+        // Single-symbol tables, so only the extra bits are in the stream: LL
+        // code 16 (16 + 1 bit), OF code 4 (16 + 4 bits), ML code 32 (35 + 1 bit).
+        let ll_table = rzstd_fse::DecodingTable::rle(16);
+        let of_table = rzstd_fse::DecodingTable::rle(4);
+        let ml_table = rzstd_fse::DecodingTable::rle(32);
+
+        // Written last sequence first, each one's bits in the reverse of the
+        // order they are read in.
+        let mut w = rzstd_io::ReverseBitWriter::new();
+        for (ll, of, ml) in [(0, 0, 0), (1, 5, 1)] {
+            w.write(ll, 1);
+            w.write(ml, 1);
+            w.write(of, 4);
+        }
+        let src = w.finish();
+
+        let mut r = rzstd_io::ReverseBitReader::new(&src)?;
+        let mut decoder = SequenceDecoder::new(
+            &ll_table,
+            &of_table,
+            &ml_table,
+            RepeatOffsets([1, 4, 8]),
+            &mut r,
+        )?;
+
+        // Offset values 21 and 16 are new offsets, 3 above the actual ones.
+        let first = decoder.next(&mut r)?;
+        assert_eq!(
+            first,
+            Sequence {
+                lit_len: 17,
+                offset: 18,
+                match_len: 36,
+            }
+        );
+        let second = decoder.next(&mut r)?;
+        assert_eq!(
+            second,
+            Sequence {
+                lit_len: 16,
+                offset: 13,
+                match_len: 35,
+            }
+        );
+        assert_eq!(r.bits_remaining(), 0);
+        assert_eq!(decoder.offsets(), RepeatOffsets([13, 18, 1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeat_offsets() -> Result<(), Error> {
+        let mut offsets = RepeatOffsets([1, 4, 8]);
+
+        // With literals, 1 to 3 pick the matching repeat offset.
+        assert_eq!(offsets.resolve(1, 5)?, 1);
+        assert_eq!(offsets, RepeatOffsets([1, 4, 8]));
+        assert_eq!(offsets.resolve(3, 5)?, 8);
+        assert_eq!(offsets, RepeatOffsets([8, 1, 4]));
+
+        // Without, they are shifted by one, 3 standing for the first minus 1.
+        assert_eq!(offsets.resolve(1, 0)?, 1);
+        assert_eq!(offsets, RepeatOffsets([1, 8, 4]));
+        assert_eq!(offsets.resolve(2, 0)?, 4);
+        assert_eq!(offsets, RepeatOffsets([4, 1, 8]));
+        assert_eq!(offsets.resolve(3, 0)?, 3);
+        assert_eq!(offsets, RepeatOffsets([3, 4, 1]));
+
+        // Which must not leave an offset of 0.
+        let mut offsets = RepeatOffsets([1, 4, 8]);
+        assert!(matches!(offsets.resolve(3, 0), Err(Error::Corruption)));
+
+        assert_eq!(offsets.resolve(103, 0)?, 100);
+        assert_eq!(offsets.0[0], 100);
+        Ok(())
+    }
+
+    #[test]
+    fn test_of_table_above_max_accuracy_log() {
+        // Accuracy log 9, but OF tables are capped at 8.
+        let src = [0x04, 0x00, 0x00, 0x00];
+        let mut curr: Option<rzstd_fse::DecodingTable<256>> = None;
+
+        let ret = update_table(Mode::FSECompressed, OF_DIST, &src, &mut curr, u8::MAX);
+        assert!(matches!(
+            ret,
+            Err(Error::FSE(rzstd_fse::Error::AccuracyLogMismatch(8, 9)))
+        ));
+        assert!(curr.is_none());
+    }
+
+    #[test]
+    fn test_of_table_too_many_symbols() -> Result<(), Error> {
+        // 40 offset codes, of the 32 there are.
+        let mut counts = vec![1i16; 39];
+        counts.push(64 - 39);
+        let table = rzstd_fse::EncodingTable::from_counts(&counts, 6)?;
+        let mut src = Vec::new();
+        table.write_distribution(&mut src);
+
+        let mut curr: Option<rzstd_fse::DecodingTable<256>> = None;
+        let ret = update_table(Mode::FSECompressed, OF_DIST, &src, &mut curr, u8::MAX);
+        assert!(matches!(
+            ret,
+            Err(Error::FSE(rzstd_fse::Error::TooManySymbols))
+        ));
+        assert!(curr.is_none());
+
+        // Too many for the 36 literal length codes too, but not for the 53
+        // match length ones.
+        let mut curr: Option<rzstd_fse::DecodingTable<512>> = None;
+        let ret = update_table(Mode::FSECompressed, LL_DIST, &src, &mut curr, u8::MAX);
+        assert!(matches!(
+            ret,
+            Err(Error::FSE(rzstd_fse::Error::TooManySymbols))
+        ));
+        let mut curr: Option<rzstd_fse::DecodingTable<512>> = None;
+        update_table(Mode::FSECompressed, ML_DIST, &src, &mut curr, u8::MAX)?;
+        assert!(curr.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_size_pairings() -> Result<(), Error> {
+        let mut fse = crate::context::FSEContext {
+            ll: None,
+            ml: None,
+            of: None,
+        };
+        update_table(Mode::Predefined, LL_DIST, &[], &mut fse.ll, u8::MAX)?;
+        update_table(Mode::Predefined, ML_DIST, &[], &mut fse.ml, u8::MAX)?;
+        update_table(Mode::Predefined, OF_DIST, &[], &mut fse.of, u8::MAX)?;
+
+        // LL tables have room for 512 entries, OF ones for 256.
+        let mut of: Option<rzstd_fse::DecodingTable<256>> = None;
+        assert!(matches!(
+            update_table(Mode::Predefined, LL_DIST, &[], &mut of, u8::MAX),
+            Err(Error::Corruption)
+        ));
+        assert!(matches!(
+            update_table(Mode::RLE, ML_DIST, &[3], &mut of, u8::MAX),
+            Err(Error::Corruption)
+        ));
+        assert!(of.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ll_table_within_max_accuracy_log() {
+        let src = [0x04, 0x00, 0x00, 0x00];
+        let mut curr: Option<rzstd_fse::DecodingTable<512>> = None;
+
+        let ret = update_table(Mode::FSECompressed, LL_DIST, &src, &mut curr, u8::MAX);
+        assert!(!matches!(
+            ret,
+            Err(Error::FSE(rzstd_fse::Error::AccuracyLogMismatch(..)))
+        ));
+    }
+
+    #[test]
+    fn test_table_above_configured_max_accuracy_log() {
+        // Accuracy log 9, within what LL tables allow.
+        let src = [0x04, 0x00, 0x00, 0x00];
+        let mut curr: Option<rzstd_fse::DecodingTable<512>> = None;
+
+        let ret = update_table(Mode::FSECompressed, LL_DIST, &src, &mut curr, 8);
+        assert!(matches!(
+            ret,
+            Err(Error::FSE(rzstd_fse::Error::AccuracyLogMismatch(8, 9)))
+        ));
+        assert!(curr.is_none());
+
+        // Predefined tables aren't read from the stream.
+        update_table(Mode::Predefined, LL_DIST, &[], &mut curr, 5)
+            .expect("predefined tables ignore the ceiling");
+    }
+}
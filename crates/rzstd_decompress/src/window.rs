@@ -1,18 +1,103 @@
-use crate::{MAX_BLOCK_SIZE, prelude::*};
+use std::ops::{Deref, DerefMut};
+
+use crate::{MAX_BLOCK_SIZE, MIN_WINDOW_SIZE, WindowStore, prelude::*};
+
+/// Size of the repeated pattern matches with offsets up to half of it are
+/// copied through.
+const SHORT_PATTERN_LEN: usize = 16;
+
+/// The in-memory stores are matched on directly rather than called through
+/// [WindowStore], which costs every match copied a virtual call. Other stores
+/// are `'static` so that, as with a borrowed buffer, dropping the window can't
+/// touch anything it borrows, and the borrow ends with its last use.
+#[derive(Debug)]
+enum Buffer<'b> {
+    Borrowed(&'b mut [u8]),
+    Owned(Vec<u8>),
+    Store(Box<dyn WindowStore>),
+}
+
+impl Buffer<'_> {
+    #[inline(always)]
+    fn is_growable(&self) -> bool {
+        match self {
+            Buffer::Borrowed(_) => false,
+            Buffer::Owned(_) => true,
+            Buffer::Store(store) => store.is_growable(),
+        }
+    }
+
+    fn grow(&mut self, len: usize) -> Result<(), Error> {
+        match self {
+            Buffer::Borrowed(_) => Ok(()),
+            Buffer::Owned(buf) => buf.grow(len),
+            Buffer::Store(store) => store.grow(len),
+        }
+    }
+}
+
+impl Deref for Buffer<'_> {
+    type Target = [u8];
+
+    #[inline(always)]
+    fn deref(&self) -> &[u8] {
+        match self {
+            Buffer::Borrowed(buf) => buf,
+            Buffer::Owned(buf) => buf,
+            Buffer::Store(store) => store.as_slice(),
+        }
+    }
+}
+
+impl DerefMut for Buffer<'_> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            Buffer::Borrowed(buf) => buf,
+            Buffer::Owned(buf) => buf,
+            Buffer::Store(store) => store.as_mut_slice(),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Window<'b> {
-    buf: &'b mut [u8],
+    buf: Buffer<'b>,
     size: usize,
     index: usize,
+    /// Bytes dropped from the front of the buffer by shifts since the last
+    /// reset.
+    discarded: u64,
+    /// Whether the whole frame fits in `size` bytes, leaving no room for
+    /// anything beyond it.
+    single_segment: bool,
 }
 
 impl<'b> Window<'b> {
     pub fn new(buf: &'b mut [u8], size: usize) -> Self {
+        Self::with_buffer(Buffer::Borrowed(buf), size)
+    }
+
+    /// A window owning its buffer, which starts out small and only grows
+    /// toward `size + MAX_BLOCK_SIZE` as output is produced.
+    pub fn growable(size: usize) -> Self {
+        let buf = vec![0; (MIN_WINDOW_SIZE + MAX_BLOCK_SIZE as u64) as usize];
+        Self::with_buffer(Buffer::Owned(buf), size)
+    }
+
+    /// A window held in `store`, which, if growable, only grows toward
+    /// `size + MAX_BLOCK_SIZE` as output is produced.
+    pub fn with_store(store: impl WindowStore + 'static, size: usize) -> Self {
+        Self::with_buffer(Buffer::Store(Box::new(store)), size)
+    }
+
+    fn with_buffer(buf: Buffer<'b>, size: usize) -> Self {
         Self {
             buf,
             size,
             index: 0,
+            discarded: 0,
+            single_segment: false,
         }
     }
 
@@ -21,20 +106,132 @@ impl<'b> Window<'b> {
         self.index
     }
 
-    pub fn reset(&mut self, size: usize) {
-        assert!(self.buf.len() >= size + MAX_BLOCK_SIZE as usize);
+    #[inline(always)]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Total number of bytes written since the last reset, including those
+    /// shifted out of the buffer.
+    #[inline(always)]
+    pub fn position(&self) -> u64 {
+        self.discarded + self.index as u64
+    }
+
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Bytes that fit after the current position without growing or shifting
+    /// the buffer.
+    #[inline(always)]
+    pub fn remaining_capacity(&self) -> usize {
+        self.capacity() - self.index
+    }
+
+    /// Whether writing `incoming` more bytes would shift the history down,
+    /// moving everything before the current position. A growable store that
+    /// can still grow to fit them doesn't shift.
+    #[inline(always)]
+    pub fn will_shift(&self, incoming: usize) -> bool {
+        let mut capacity = self.buf.len();
+        if self.buf.is_growable() {
+            capacity = capacity.max(self.limit());
+        }
+        self.index + incoming > capacity
+    }
+
+    /// The most a growable store grows to: the window plus a block, or just
+    /// the window for a single-segment frame.
+    #[inline(always)]
+    fn limit(&self) -> usize {
+        if self.single_segment {
+            return self.size;
+        }
+        self.size.saturating_add(MAX_BLOCK_SIZE as usize)
+    }
+
+    /// Starts a new frame with a window of `size` bytes, failing if a fixed
+    /// store is too small to hold it. A growable one grows toward the new
+    /// size as output is produced.
+    pub fn reset(&mut self, size: usize) -> Result<(), Error> {
+        let needed = (size as u64).saturating_add(MAX_BLOCK_SIZE as u64);
+        self.start(size, needed, false)
+    }
+
+    /// Starts a new single-segment frame, whose content size of `size` bytes
+    /// doubles as the window. As the whole output fits in one segment, a
+    /// fixed store only needs to hold `size` bytes, without room for an extra
+    /// block.
+    pub fn reset_single_segment(&mut self, size: usize) -> Result<(), Error> {
+        self.start(size, size as u64, true)
+    }
+
+    fn start(
+        &mut self,
+        size: usize,
+        needed: u64,
+        single_segment: bool,
+    ) -> Result<(), Error> {
+        if !self.buf.is_growable() && (self.buf.len() as u64) < needed {
+            return Err(Error::WindowBufferTooSmall {
+                needed,
+                have: self.buf.len(),
+            });
+        }
 
         self.size = size;
         self.index = 0;
+        self.discarded = 0;
+        self.single_segment = single_segment;
+        Ok(())
+    }
+
+    /// Makes room for `len` more bytes, growing a growable store before
+    /// falling back to shifting the history down.
+    ///
+    /// A single-segment frame never shifts: output past its content size is
+    /// reported as [Error::Corruption], however large the buffer.
+    #[inline(always)]
+    fn reserve(&mut self, len: usize) -> Result<(), Error> {
+        if self.single_segment && self.index + len > self.size {
+            return Err(Error::Corruption);
+        }
+
+        if len <= self.remaining_capacity() {
+            return Ok(());
+        }
+
+        let limit = self.limit();
+        let have = self.buf.len();
+        if self.buf.is_growable() && have < limit {
+            // Growing keeps the history in place, so offsets into it stay
+            // valid.
+            let new_len = (self.index + len).max(have * 2).min(limit);
+            self.buf.grow(new_len)?;
+
+            if self.index + len <= self.buf.len() {
+                return Ok(());
+            }
+        }
+
+        self.shift();
+        if len > self.remaining_capacity() {
+            return Err(Error::Corruption);
+        }
+        Ok(())
     }
 
     #[inline(always)]
     fn shift(&mut self) {
+        debug_assert!(!self.single_segment, "single-segment frames never shift");
         if self.index <= self.size {
             return;
         }
 
         self.buf.copy_within(self.index - self.size..self.index, 0);
+        self.discarded += (self.index - self.size) as u64;
         self.index = self.size;
     }
 
@@ -49,9 +246,7 @@ impl<'b> Window<'b> {
         src: &mut impl rzstd_io::Reader,
         len: usize,
     ) -> Result<(), Error> {
-        if self.index + len > self.buf.len() {
-            self.shift();
-        }
+        self.reserve(len)?;
 
         let target = &mut self.buf[self.index..self.index + len];
         src.read_exact(target)?;
@@ -62,20 +257,17 @@ impl<'b> Window<'b> {
     }
 
     #[inline(always)]
-    pub fn push_buf(&mut self, data: &[u8]) {
-        if self.index + data.len() > self.buf.len() {
-            self.shift();
-        }
+    pub fn push_buf(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.reserve(data.len())?;
 
         self.buf[self.index..self.index + data.len()].copy_from_slice(data);
         self.index += data.len();
+        Ok(())
     }
 
     #[inline(always)]
-    pub fn push_rle(&mut self, byte: u8, count: usize) {
-        if self.index + count > self.buf.len() {
-            self.shift();
-        }
+    pub fn push_rle(&mut self, byte: u8, count: usize) -> Result<(), Error> {
+        self.reserve(count)?;
 
         self.buf[self.index..self.index + count].fill(byte);
         tracing::debug!(
@@ -84,16 +276,20 @@ impl<'b> Window<'b> {
             &self.buf[self.index..self.index + count]
         );
 
-        self.index += count
+        self.index += count;
+        Ok(())
     }
 
+    /// Appends `n_bytes` starting `offset` bytes before the current position.
+    ///
+    /// Room is made before the offset is checked: a shift keeps the last
+    /// `size` bytes right before the current position, so an offset resolves
+    /// to the same byte whether or not one happened in between.
     #[inline(always)]
     pub fn copy_within(&mut self, offset: usize, n_bytes: usize) -> Result<(), Error> {
         debug_assert!(n_bytes <= MAX_BLOCK_SIZE as usize);
 
-        if self.index + n_bytes > self.buf.len() {
-            self.shift();
-        }
+        self.reserve(n_bytes)?;
 
         let available = self.index.min(self.size);
         if offset == 0 || offset > available {
@@ -106,6 +302,22 @@ impl<'b> Window<'b> {
         } else if offset == 1 {
             let val = self.buf[start];
             self.buf[self.index..self.index + n_bytes].fill(val);
+        } else if offset <= SHORT_PATTERN_LEN / 2 {
+            // The match repeats its first `offset` bytes, so whole copies of
+            // them can be written at once rather than doubling up to them.
+            let mut pattern = [0; SHORT_PATTERN_LEN];
+            for (i, byte) in pattern.iter_mut().enumerate() {
+                *byte = self.buf[start + i % offset];
+            }
+            let step = SHORT_PATTERN_LEN - SHORT_PATTERN_LEN % offset;
+
+            let dst = &mut self.buf[self.index..self.index + n_bytes];
+            let mut chunks = dst.chunks_exact_mut(step);
+            for chunk in &mut chunks {
+                chunk.copy_from_slice(&pattern[..step]);
+            }
+            let rest = chunks.into_remainder();
+            rest.copy_from_slice(&pattern[..rest.len()]);
         } else {
             let initial_copy = std::cmp::min(offset, n_bytes);
             self.buf
@@ -124,3 +336,189 @@ impl<'b> Window<'b> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const SIZE: usize = 16;
+    const CAPACITY: usize = SIZE + MAX_BLOCK_SIZE as usize;
+
+    #[test]
+    fn test_shift_boundary() -> Result<(), Error> {
+        let mut buf = vec![0; CAPACITY];
+        let mut window = Window::new(&mut buf, SIZE);
+
+        window.push_rle(1, CAPACITY - 10)?;
+        assert_eq!(window.remaining_capacity(), 10);
+        assert!(!window.will_shift(10));
+        assert!(window.will_shift(11));
+
+        window.push_rle(2, 10)?;
+        assert_eq!(window.remaining_capacity(), 0);
+        assert_eq!(window.index(), CAPACITY);
+
+        // Keeps the last `SIZE` bytes, then appends after them.
+        assert!(window.will_shift(1));
+        window.push_buf(&[3])?;
+        assert_eq!(window.index(), SIZE + 1);
+        assert_eq!(window.as_slice()[..SIZE - 10], [1; SIZE - 10]);
+        assert_eq!(window.as_slice()[SIZE - 10..SIZE], [2; 10]);
+        assert_eq!(window.as_slice()[SIZE], 3);
+        assert_eq!(window.position(), CAPACITY as u64 + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_growable_shift_boundary() -> Result<(), Error> {
+        let size = 1 << 20;
+        let mut window = Window::growable(size);
+        let initial = window.capacity();
+
+        // Past the allocation, but the buffer grows instead of shifting.
+        assert!(!window.will_shift(initial + 1));
+        assert!(!window.will_shift(size + MAX_BLOCK_SIZE as usize));
+        assert!(window.will_shift(size + MAX_BLOCK_SIZE as usize + 1));
+
+        window.push_rle(1, initial + 1)?;
+        assert!(window.capacity() > initial);
+        assert_eq!(window.index(), initial + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_segment_tight_buffer() -> Result<(), Error> {
+        let mut buf = vec![0; SIZE];
+        let mut window = Window::new(&mut buf, SIZE);
+        assert!(matches!(
+            window.reset(SIZE),
+            Err(Error::WindowBufferTooSmall { .. })
+        ));
+
+        window.reset_single_segment(SIZE)?;
+        window.push_rle(1, 4)?;
+        window.copy_within(4, SIZE - 4)?;
+        assert_eq!(window.as_slice(), [1; SIZE]);
+
+        // Output past the content size has nowhere to go.
+        assert!(matches!(window.push_buf(&[2]), Err(Error::Corruption)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_segment_never_shifts() -> Result<(), Error> {
+        let mut buf = vec![0; CAPACITY];
+        let mut window = Window::new(&mut buf, SIZE);
+
+        // Even with room to spare, nothing goes past the content size.
+        window.reset_single_segment(SIZE)?;
+        window.push_rle(1, SIZE - 1)?;
+        assert!(matches!(window.push_rle(2, 2), Err(Error::Corruption)));
+        assert!(matches!(window.copy_within(1, 2), Err(Error::Corruption)));
+        window.push_buf(&[2])?;
+        assert_eq!(window.index(), SIZE);
+
+        let mut window = Window::growable(SIZE);
+        window.reset_single_segment(SIZE)?;
+        assert!(matches!(
+            window.push_buf(&[0; SIZE + 1]),
+            Err(Error::Corruption)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_across_shift() -> Result<(), Error> {
+        let mut buf = vec![0; CAPACITY];
+        let mut window = Window::new(&mut buf, SIZE);
+
+        window.push_rle(0, CAPACITY - SIZE - 4)?;
+        let history = (1..=SIZE as u8).collect::<Vec<_>>();
+        window.push_buf(&history)?;
+
+        // The literals shift the window, the match then reaches back past
+        // them into the oldest byte still kept.
+        window.push_buf(&[0xaa; 8])?;
+        assert_eq!(window.index(), SIZE + 8);
+        window.copy_within(SIZE, 4)?;
+        assert_eq!(window.as_slice()[SIZE + 8..], history[8..12]);
+
+        assert!(matches!(
+            window.copy_within(SIZE + 1, 1),
+            Err(Error::CopiedSizeOutOfBounds)
+        ));
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn test_short_offset_matches_reference(
+            history in proptest::collection::vec(any::<u8>(), 1..=SIZE),
+            offset in 1..=9usize,
+            len in 0..=100usize,
+        ) {
+            let offset = offset.min(history.len());
+            let mut buf = vec![0; CAPACITY];
+            let mut window = Window::new(&mut buf, SIZE);
+            prop_assert!(window.push_buf(&history).is_ok());
+            prop_assert!(window.copy_within(offset, len).is_ok());
+
+            // One byte at a time, each possibly copied just before.
+            let mut expected = history;
+            for _ in 0..len {
+                expected.push(expected[expected.len() - offset]);
+            }
+            prop_assert_eq!(window.as_slice(), &expected[..]);
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Literals(u8, usize),
+        Match(usize, usize),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            (any::<u8>(), 0..=MAX_BLOCK_SIZE as usize / 2)
+                .prop_map(|(byte, len)| Op::Literals(byte, len)),
+            (1..=SIZE + 2, 0..=MAX_BLOCK_SIZE as usize / 2)
+                .prop_map(|(offset, len)| Op::Match(offset, len)),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_unbounded_history(ops in proptest::collection::vec(op(), 1..16)) {
+            let mut buf = vec![0; CAPACITY];
+            let mut window = Window::new(&mut buf, SIZE);
+            let mut history = Vec::new();
+
+            for op in ops {
+                match op {
+                    Op::Literals(byte, len) => {
+                        let literals = (0..len).map(|i| byte.wrapping_add(i as u8));
+                        let literals = literals.collect::<Vec<_>>();
+                        prop_assert!(window.push_buf(&literals).is_ok());
+                        history.extend(literals);
+                    }
+                    Op::Match(offset, len) => {
+                        let valid = offset <= history.len().min(SIZE);
+                        prop_assert_eq!(window.copy_within(offset, len).is_ok(), valid);
+                        if valid {
+                            for _ in 0..len {
+                                history.push(history[history.len() - offset]);
+                            }
+                        }
+                    }
+                }
+
+                prop_assert_eq!(window.position(), history.len() as u64);
+                let kept = &history[history.len() - window.index()..];
+                prop_assert_eq!(window.as_slice(), kept);
+            }
+        }
+    }
+}
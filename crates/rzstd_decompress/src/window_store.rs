@@ -0,0 +1,48 @@
+use crate::prelude::*;
+
+/// Memory holding a decoder's window: the history matches copy from, and the
+/// output of the block being decoded.
+///
+/// Stores are either fixed in size, which must then hold the largest window
+/// decoded plus a block, like a caller-provided buffer, or growable, enlarged
+/// as output is produced.
+pub trait WindowStore: std::fmt::Debug + Send {
+    fn as_slice(&self) -> &[u8];
+
+    fn as_mut_slice(&mut self) -> &mut [u8];
+
+    /// Whether [WindowStore::grow] may enlarge the store.
+    fn is_growable(&self) -> bool {
+        false
+    }
+
+    /// Enlarges the store to `len` bytes, keeping what it holds. Only called
+    /// on growable stores, with more than they hold.
+    fn grow(&mut self, len: usize) -> Result<(), Error> {
+        let _ = len;
+        Ok(())
+    }
+}
+
+/// The in-memory store windows sized from frame headers default to, which
+/// the window holds directly rather than through the trait.
+impl WindowStore for Vec<u8> {
+    #[inline(always)]
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn is_growable(&self) -> bool {
+        true
+    }
+
+    fn grow(&mut self, len: usize) -> Result<(), Error> {
+        self.resize(len, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+//! Round-trips a fixture through the reference `zstd` CLI with option
+//! combinations the Silesia corpus doesn't cover. Skipped when `zstd` isn't
+//! installed.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const OPTIONS: &[&[&str]] = &[
+    &[],
+    &["-1"],
+    &["-19"],
+    &["--fast=5"],
+    &["--no-check"],
+    &["--no-content-size"],
+    &["--no-check", "--no-content-size"],
+    &["--long=27"],
+    &["-T0"],
+    &["-T0", "-B65536"],
+    &["-T0", "-B65536", "--no-content-size"],
+    &["--ultra", "-22"],
+    &["--zstd=wlog=10"],
+    &["--zstd=wlog=17,tlen=999"],
+    &["--exclude-compressed"],
+];
+
+fn zstd_available() -> bool {
+    Command::new("zstd")
+        .arg("--version")
+        .output()
+        .is_ok_and(|out| out.status.success())
+}
+
+/// Text-like runs, incompressible noise, and a copy of the start far enough
+/// away that only long-distance matching finds it.
+fn fixture() -> Vec<u8> {
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    let words = [
+        "zstd ",
+        "frame ",
+        "block ",
+        "literal ",
+        "sequence ",
+        "window ",
+    ];
+    let mut out = Vec::new();
+    while out.len() < 256 * 1024 {
+        out.extend_from_slice(words[next() as usize % words.len()].as_bytes());
+    }
+    out.extend((0..256 * 1024).map(|_| next() as u8));
+    out.extend_from_slice(&[0x2a; 10_000]);
+    out.extend_from_within(..200 * 1024);
+    out
+}
+
+fn compress(path: &Path, options: &[&str]) -> Vec<u8> {
+    let out = Command::new("zstd")
+        .args(options)
+        .args(["-q", "-c"])
+        .arg(path)
+        .output()
+        .expect("zstd should run");
+    assert!(
+        out.status.success(),
+        "zstd {options:?} failed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    out.stdout
+}
+
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn zstd_cli_round_trip() {
+    if !zstd_available() {
+        eprintln!("zstd not found, skipping");
+        return;
+    }
+
+    let src = fixture();
+    let file = TempFile(
+        std::env::temp_dir().join(format!("rzstd-cli-fixture-{}", std::process::id())),
+    );
+    std::fs::write(&file.0, &src).expect("temp dir should be writable");
+
+    for options in OPTIONS {
+        let compressed = compress(&file.0, options);
+        let decompressed = rzstd_decompress::decompress(&compressed)
+            .unwrap_or_else(|err| panic!("zstd {options:?}: {err}"));
+
+        assert!(decompressed == src, "zstd {options:?}: output differs");
+    }
+}
+
+#[test]
+fn zstd_cli_concatenated_frames() {
+    if !zstd_available() {
+        eprintln!("zstd not found, skipping");
+        return;
+    }
+
+    let src = fixture();
+    let file = TempFile(
+        std::env::temp_dir().join(format!("rzstd-cli-frames-{}", std::process::id())),
+    );
+    std::fs::write(&file.0, &src).expect("temp dir should be writable");
+
+    let compressed = [
+        compress(&file.0, &["-3"]),
+        compress(&file.0, &["--no-check", "--no-content-size"]),
+    ]
+    .concat();
+
+    let decompressed =
+        rzstd_decompress::decompress(&compressed).expect("frames should decode");
+    assert!(decompressed == [&src[..], &src[..]].concat());
+}
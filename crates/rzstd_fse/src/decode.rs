@@ -3,8 +3,9 @@ use rzstd_io::{BitReader, ReverseBitReader};
 
 use crate::Error;
 
-const MAX_SYMBOLS: usize = 256;
-const ACCURACY_LOG_RANGE: std::ops::RangeInclusive<u8> = 5..=15;
+pub(crate) const MAX_SYMBOLS: usize = 256;
+pub(crate) const ACCURACY_LOG_RANGE: std::ops::RangeInclusive<u8> = 5..=15;
+pub(crate) const MAX_TABLE_SIZE: usize = 1 << *ACCURACY_LOG_RANGE.end();
 
 #[derive(Debug, Clone, Copy)]
 #[repr(transparent)]
@@ -53,6 +54,23 @@ impl<'t, const N: usize> Decoder<'t, N> {
     pub fn bits_required(&self) -> u8 {
         self.table[self.state].n_bits
     }
+
+    /// Returns the current symbol and moves on to the next state, or `None`,
+    /// staying put, once `src` lacks the bits to: the current symbol is then
+    /// the last one the state yields.
+    #[inline(always)]
+    pub fn decode_symbol(
+        &mut self,
+        src: &mut ReverseBitReader,
+    ) -> Result<Option<u8>, Error> {
+        if self.bits_required() as usize > src.bits_remaining() {
+            return Ok(None);
+        }
+
+        let symbol = self.peek();
+        self.update(src)?;
+        Ok(Some(symbol))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -65,10 +83,24 @@ pub struct NormalizedDistribution<const N: usize> {
 }
 
 impl<const N: usize> NormalizedDistribution<N> {
-    pub fn read(src: &mut BitReader) -> Result<Self, Error> {
+    /// Reads a distribution whose accuracy log may not exceed
+    /// `max_accuracy_log`, nor what a table of `N` entries can hold.
+    pub fn read(src: &mut BitReader, max_accuracy_log: u8) -> Result<Self, Error> {
+        Self::read_with_max_symbols(src, max_accuracy_log, MAX_SYMBOLS)
+    }
+
+    /// Like [NormalizedDistribution::read], but fails with
+    /// [Error::TooManySymbols] as soon as the distribution declares more than
+    /// `max_symbols` symbols, for alphabets smaller than a byte.
+    pub fn read_with_max_symbols(
+        src: &mut BitReader,
+        max_accuracy_log: u8,
+        max_symbols: usize,
+    ) -> Result<Self, Error> {
         assert!(N.is_power_of_two());
+        let max_symbols = max_symbols.min(MAX_SYMBOLS);
 
-        let max_accuracy_log = N.trailing_zeros() as u8;
+        let max_accuracy_log = max_accuracy_log.min(N.trailing_zeros() as u8);
         let read = src.read(4)? as u8;
         let accuracy_log = 5 + read;
 
@@ -84,7 +116,7 @@ impl<const N: usize> NormalizedDistribution<N> {
 
         let mut remaining: i32 = 1 << accuracy_log;
         while remaining > 0 {
-            if symbol_idx >= MAX_SYMBOLS {
+            if symbol_idx >= max_symbols {
                 return Err(Error::TooManySymbols);
             }
 
@@ -195,6 +227,10 @@ impl<const N: usize> NormalizedDistribution<N> {
     }
 
     pub fn from_predefined(counts: &[i16], accuracy_log: u8) -> Result<Self, Error> {
+        if !ACCURACY_LOG_RANGE.contains(&accuracy_log) {
+            return Err(Error::InvalidAccuracyLog(accuracy_log));
+        }
+
         let mut final_counts = [0i16; MAX_SYMBOLS];
         let mut symbol_state = [0u16; MAX_SYMBOLS];
         let mut symbol_count = 0;
@@ -229,9 +265,9 @@ impl<const N: usize> NormalizedDistribution<N> {
 #[derive(Clone, Copy)]
 #[repr(align(4))]
 pub struct Entry {
-    baseline: u16,
-    n_bits: u8,
-    symbol: u8,
+    pub(crate) baseline: u16,
+    pub(crate) n_bits: u8,
+    pub(crate) symbol: u8,
 }
 
 impl std::fmt::Debug for Entry {
@@ -248,21 +284,69 @@ const_assert!(std::mem::size_of::<Entry>() == 4);
 const_assert!(std::mem::align_of::<Entry>() == 4);
 
 #[repr(align(64))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DecodingTable<const N: usize> {
     entries: [Entry; N],
     accuracy_log: u8,
 }
 
-const_assert!(std::mem::size_of::<DecodingTable<512>>() % 64 == 0);
+const_assert!(std::mem::size_of::<DecodingTable<512>>().is_multiple_of(64));
 
 impl<const N: usize> DecodingTable<N> {
-    pub fn read(r: &mut rzstd_io::BitReader, count: usize) -> Result<Self, Error> {
-        let mut dist = NormalizedDistribution::<N>::read(r)?;
-        if r.bytes_consumed() > count {
+    /// Reads a table description of at most `count` bytes, returning the
+    /// table along with the number of bytes it actually spanned.
+    pub fn read(
+        r: &mut rzstd_io::BitReader,
+        count: usize,
+        max_accuracy_log: u8,
+    ) -> Result<(Self, usize), Error> {
+        Self::read_with_max_symbols(r, count, max_accuracy_log, MAX_SYMBOLS)
+    }
+
+    /// Like [DecodingTable::read], for a distribution of at most
+    /// `max_symbols` symbols. See
+    /// [NormalizedDistribution::read_with_max_symbols].
+    pub fn read_with_max_symbols(
+        r: &mut rzstd_io::BitReader,
+        count: usize,
+        max_accuracy_log: u8,
+        max_symbols: usize,
+    ) -> Result<(Self, usize), Error> {
+        let mut dist = NormalizedDistribution::<N>::read_with_max_symbols(
+            r,
+            max_accuracy_log,
+            max_symbols,
+        )?;
+        let consumed = r.bytes_consumed();
+        if consumed > count {
             return Err(Error::Corruption);
         }
 
+        Ok((Self::from_distribution(&mut dist)?, consumed))
+    }
+
+    /// Builds the table for a known normalized distribution, as it would be
+    /// described in the stream: `-1` marks a low-probability symbol, which
+    /// counts as 1 toward the `1 << accuracy_log` total.
+    pub fn from_counts(counts: &[i16], accuracy_log: u8) -> Result<Self, Error> {
+        let max_accuracy_log = N.trailing_zeros() as u8;
+        if accuracy_log > max_accuracy_log {
+            return Err(Error::AccuracyLogMismatch(max_accuracy_log, accuracy_log));
+        }
+
+        if let Some(&c) = counts.iter().find(|&&c| c < -1) {
+            return Err(Error::InvalidCount(c));
+        }
+        let sum = counts
+            .iter()
+            .map(|&c| if c == -1 { 1 } else { c as i32 })
+            .sum::<i32>();
+        if sum != 1 << accuracy_log {
+            return Err(Error::SumMismatch((1 << accuracy_log) - sum));
+        }
+
+        let mut dist =
+            NormalizedDistribution::<N>::from_predefined(counts, accuracy_log)?;
         Self::from_distribution(&mut dist)
     }
 
@@ -295,20 +379,50 @@ impl<const N: usize> DecodingTable<N> {
             baseline: 0,
         }; N];
 
-        let table = &mut entries[..(1 << accuracy_log) as usize];
+        Self::build_entries(dist, &mut entries[..(1 << accuracy_log) as usize])?;
 
+        Ok(Self {
+            entries,
+            accuracy_log,
+        })
+    }
+
+    /// Like [DecodingTable::from_distribution], but builds the table straight
+    /// on the heap, so large tables never pass through the stack: one of
+    /// 15-bit accuracy takes 128 KiB.
+    pub fn boxed_from_distribution(
+        dist: &mut NormalizedDistribution<N>,
+    ) -> Result<Box<Self>, Error> {
+        assert!(N.is_power_of_two());
+        let accuracy_log = dist.accuracy_log;
+
+        if !ACCURACY_LOG_RANGE.contains(&accuracy_log) {
+            return Err(Error::InvalidAccuracyLog(accuracy_log));
+        }
+        let max_accuracy_log = N.trailing_zeros() as u8;
+        if accuracy_log > max_accuracy_log {
+            return Err(Error::AccuracyLogMismatch(max_accuracy_log, accuracy_log));
+        }
+
+        // SAFETY: the table is nothing but integers, which are valid zeroed.
+        let mut table = unsafe { Box::<Self>::new_zeroed().assume_init() };
+        Self::build_entries(dist, &mut table.entries[..(1 << accuracy_log) as usize])?;
+        table.accuracy_log = accuracy_log;
+
+        Ok(table)
+    }
+
+    fn build_entries(
+        dist: &mut NormalizedDistribution<N>,
+        table: &mut [Entry],
+    ) -> Result<(), Error> {
         if !dist.has_low_prob {
             Self::spread_weights(dist, table)?;
         } else {
             Self::spread_symbols_low_prob(dist, table)?;
         }
 
-        Self::finalize_table(table, &mut dist.symbol_state, accuracy_log)?;
-
-        Ok(Self {
-            entries,
-            accuracy_log,
-        })
+        Self::finalize_table(table, &mut dist.symbol_state, dist.accuracy_log)
     }
 
     fn spread_weights(
@@ -430,7 +544,7 @@ impl<const N: usize> DecodingTable<N> {
             let n_bits = (accuracy_log + state.leading_zeros() as u8) - 15;
 
             entry.n_bits = n_bits;
-            entry.baseline = (state << n_bits).wrapping_sub(n as u16);
+            entry.baseline = (state << n_bits).wrapping_sub(n);
         }
 
         Ok(())
@@ -444,6 +558,14 @@ impl<const N: usize> DecodingTable<N> {
     pub fn table(&self) -> &[Entry] {
         &self.entries[..(1 << self.accuracy_log)]
     }
+
+    /// The `(symbol, n_bits, baseline)` decoded at `state`, or `None` if the
+    /// state is outside the table.
+    pub fn entry(&self, state: u16) -> Option<(u8, u8, u16)> {
+        self.table()
+            .get(state as usize)
+            .map(|e| (e.symbol, e.n_bits, e.baseline))
+    }
 }
 
 impl<const N: usize> std::ops::Index<State> for DecodingTable<N> {
@@ -456,6 +578,122 @@ impl<const N: usize> std::ops::Index<State> for DecodingTable<N> {
     }
 }
 
+/// A heap-backed [DecodingTable] whose capacity is picked at runtime from the
+/// accuracy log read from the stream, rather than fixed at the type level.
+#[derive(Debug)]
+pub struct DynDecodingTable {
+    entries: Vec<Entry>,
+    accuracy_log: u8,
+}
+
+impl DynDecodingTable {
+    /// Like [DecodingTable::read].
+    pub fn read(
+        r: &mut rzstd_io::BitReader,
+        count: usize,
+        max_accuracy_log: u8,
+    ) -> Result<(Self, usize), Error> {
+        let mut dist =
+            NormalizedDistribution::<MAX_TABLE_SIZE>::read(r, max_accuracy_log)?;
+        let consumed = r.bytes_consumed();
+        if consumed > count {
+            return Err(Error::Corruption);
+        }
+
+        Ok((Self::from_distribution(&mut dist)?, consumed))
+    }
+
+    pub fn rle(symbol: u8) -> Self {
+        let entries = vec![
+            Entry {
+                symbol,
+                n_bits: 0,
+                baseline: 0,
+            };
+            1
+        ];
+        Self {
+            entries,
+            accuracy_log: 0,
+        }
+    }
+
+    pub fn from_distribution<const N: usize>(
+        dist: &mut NormalizedDistribution<N>,
+    ) -> Result<Self, Error> {
+        let accuracy_log = dist.accuracy_log;
+
+        if !ACCURACY_LOG_RANGE.contains(&accuracy_log) {
+            return Err(Error::InvalidAccuracyLog(accuracy_log));
+        }
+
+        let mut entries = vec![
+            Entry {
+                symbol: 0,
+                n_bits: 0,
+                baseline: 0,
+            };
+            1 << accuracy_log
+        ];
+
+        DecodingTable::<N>::build_entries(dist, &mut entries)?;
+
+        Ok(Self {
+            entries,
+            accuracy_log,
+        })
+    }
+
+    const fn accuracy_log(&self) -> u8 {
+        self.accuracy_log
+    }
+
+    #[inline(always)]
+    pub fn table(&self) -> &[Entry] {
+        &self.entries
+    }
+}
+
+pub struct DynDecoder<'t> {
+    state: State,
+    table: &'t DynDecodingTable,
+}
+
+impl<'t> DynDecoder<'t> {
+    pub fn new(
+        table: &'t DynDecodingTable,
+        src: &mut ReverseBitReader,
+    ) -> Result<Self, Error> {
+        let state = {
+            let state = src.read(table.accuracy_log())?;
+            State(state as u16)
+        };
+
+        Ok(Self { table, state })
+    }
+
+    #[inline(always)]
+    pub fn peek(&self) -> u8 {
+        debug_assert!((self.state.0 as usize) < self.table.entries.len());
+        self.table.entries[self.state.0 as usize].symbol
+    }
+
+    #[inline(always)]
+    pub fn update(&mut self, src: &mut ReverseBitReader) -> Result<(), Error> {
+        debug_assert!((self.state.0 as usize) < self.table.entries.len());
+        let entry = &self.table.entries[self.state.0 as usize];
+
+        let bits = src.read(entry.n_bits)?;
+        self.state = State(entry.baseline + bits as u16);
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn bits_required(&self) -> u8 {
+        self.table.entries[self.state.0 as usize].n_bits
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use proptest::prelude::*;
@@ -523,6 +761,329 @@ mod tests {
         assert_eq!(entry_63.baseline, 0);
     }
 
+    #[test]
+    fn test_from_counts() -> Result<(), Error> {
+        // RFC 8878 Appendix A: Literal Length Code.
+        let counts = [
+            4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3,
+            2, 1, 1, 1, 1, 1, -1, -1, -1, -1,
+        ];
+        let table = DecodingTable::<64>::from_counts(&counts, 6)?;
+
+        // State | Symbol | Number_Of_Bits | Base
+        let expected = [
+            (0, 0, 4, 0),
+            (1, 0, 4, 16),
+            (2, 1, 5, 32),
+            (3, 3, 5, 0),
+            (4, 4, 5, 0),
+            (5, 6, 5, 0),
+            (60, 35, 6, 0),
+            (63, 32, 6, 0),
+        ];
+        for (state, symbol, n_bits, baseline) in expected {
+            assert_eq!(table.entry(state), Some((symbol, n_bits, baseline)));
+        }
+
+        assert!(matches!(
+            DecodingTable::<64>::from_counts(&counts[1..], 6),
+            Err(Error::SumMismatch(4))
+        ));
+        assert!(matches!(
+            DecodingTable::<32>::from_counts(&counts, 6),
+            Err(Error::AccuracyLogMismatch(5, 6))
+        ));
+        assert!(matches!(
+            DecodingTable::<32>::from_counts(&[28, -4], 5),
+            Err(Error::InvalidCount(-4))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_entry() -> Result<(), Error> {
+        // RFC 8878 Appendix A: Offset Code, predefined distribution.
+        let counts = [
+            1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1,
+            -1, -1, -1, -1,
+        ];
+        let mut dist = NormalizedDistribution::<32>::from_predefined(&counts, 5)?;
+        let table = DecodingTable::from_distribution(&mut dist)?;
+
+        // State | Symbol | Number_Of_Bits | Base
+        let expected = [
+            (0, 0, 5, 0),
+            (1, 6, 4, 0),
+            (2, 9, 5, 0),
+            (3, 15, 5, 0),
+            (6, 7, 4, 0),
+            (10, 5, 5, 0),
+            (15, 7, 4, 16),
+            (24, 6, 4, 16),
+            (27, 28, 5, 0),
+            (31, 24, 5, 0),
+        ];
+        for (state, symbol, n_bits, baseline) in expected {
+            assert_eq!(
+                table.entry(state),
+                Some((symbol, n_bits, baseline)),
+                "state {state}"
+            );
+        }
+
+        assert_eq!(table.entry(32), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_every_initial_state_is_valid() -> Result<(), Error> {
+        // Symbol 0 takes over half the table, so the states it spans past
+        // `1 << accuracy_log` need no extra bits.
+        let mut dist = NormalizedDistribution::<32>::from_predefined(&[20, 12], 5)?;
+        let table = DecodingTable::from_distribution(&mut dist)?;
+
+        let zero_bits = (0..32).filter(|&state| table.entry(state).unwrap().1 == 0);
+        assert!(zero_bits.count() > 0);
+
+        for state in 0..32 {
+            let mut w = rzstd_io::ReverseBitWriter::new();
+            w.write(1, 5);
+            w.write(state as u64, 5);
+            let src = w.finish();
+
+            let mut br = ReverseBitReader::new(&src)?;
+            let mut decoder = Decoder::new(&table, &mut br)?;
+            assert_eq!(decoder.peek(), table.entry(state).unwrap().0);
+            decoder.update(&mut br)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_symbol() -> Result<(), Error> {
+        // The weights of the RFC's Huffman example, as a compressed tree
+        // description would interleave them.
+        let weights = [4, 3, 2, 0, 1];
+        let counts = crate::EncodingTable::normalize(&[1, 1, 1, 1, 1], 5)?;
+        let encoder = crate::EncodingTable::from_counts(&counts, 5)?;
+        let src = encoder.encode_interleaved(&weights)?;
+
+        let table = DecodingTable::<32>::from_counts(&counts, 5)?;
+        let mut br = ReverseBitReader::new(&src)?;
+        let mut decoders = [
+            Decoder::new(&table, &mut br)?,
+            Decoder::new(&table, &mut br)?,
+        ];
+
+        let mut out = Vec::new();
+        let mut turn = 0;
+        while let Some(symbol) = decoders[turn].decode_symbol(&mut br)? {
+            out.push(symbol);
+            turn = 1 - turn;
+        }
+        out.push(decoders[turn].peek());
+        out.push(decoders[1 - turn].peek());
+
+        assert_eq!(out, weights);
+        assert_eq!(decoders[turn].decode_symbol(&mut br)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_boxed_from_distribution() -> Result<(), Error> {
+        const N: usize = MAX_TABLE_SIZE;
+        let counts = [16383, 8192, 4096, 4096, -1];
+        let dist = NormalizedDistribution::<N>::from_predefined(&counts, 15)?;
+
+        let boxed = DecodingTable::<N>::boxed_from_distribution(&mut dist.clone())?;
+        let dynamic = DynDecodingTable::from_distribution(&mut dist.clone())?;
+        assert_eq!(boxed.table().len(), N);
+
+        let entries = |table: &[Entry]| {
+            table
+                .iter()
+                .map(|e| (e.symbol, e.n_bits, e.baseline))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(entries(boxed.table()), entries(dynamic.table()));
+
+        let mut dist = NormalizedDistribution::<32>::from_predefined(&[32, 32], 6)?;
+        assert!(matches!(
+            DecodingTable::<32>::boxed_from_distribution(&mut dist),
+            Err(Error::AccuracyLogMismatch(5, 6))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_predefined_accuracy_log_out_of_range() {
+        let counts = [8, 8];
+        for accuracy_log in [4, 16] {
+            assert!(matches!(
+                NormalizedDistribution::<32>::from_predefined(&counts, accuracy_log),
+                Err(Error::InvalidAccuracyLog(log)) if log == accuracy_log
+            ));
+        }
+    }
+
+    #[test]
+    fn test_accuracy_log_above_declared_max() -> Result<(), Error> {
+        // Accuracy log 9, which a 512-entry table could hold.
+        let data = [0x04, 0x00, 0x00, 0x00];
+
+        let mut br = BitReader::new(&data)?;
+        assert!(matches!(
+            NormalizedDistribution::<512>::read(&mut br, 8),
+            Err(Error::AccuracyLogMismatch(8, 9))
+        ));
+
+        let mut br = BitReader::new(&data)?;
+        assert!(matches!(
+            DynDecodingTable::read(&mut br, data.len(), 8),
+            Err(Error::AccuracyLogMismatch(8, 9))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_declared_size_too_short() -> Result<(), Error> {
+        let table = crate::EncodingTable::from_counts(&counts_for(6), 6)?;
+        let mut src = Vec::new();
+        table.write_distribution(&mut src);
+        src.extend([0xAA; 8]);
+        let len = src.len() - 8;
+
+        let mut br = BitReader::new(&src)?;
+        let (_, consumed) = DecodingTable::<64>::read(&mut br, len, 6)?;
+        assert_eq!(consumed, len);
+
+        let mut br = BitReader::new(&src)?;
+        assert!(matches!(
+            DecodingTable::<64>::read(&mut br, len - 1, 6),
+            Err(Error::Corruption)
+        ));
+
+        let mut br = BitReader::new(&src)?;
+        assert!(matches!(
+            DynDecodingTable::read(&mut br, len - 1, 6),
+            Err(Error::Corruption)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_symbols() -> Result<(), Error> {
+        let counts = counts_for(6);
+        let table = crate::EncodingTable::from_counts(&counts, 6)?;
+        let mut src = Vec::new();
+        table.write_distribution(&mut src);
+
+        let mut br = BitReader::new(&src)?;
+        DecodingTable::<64>::read_with_max_symbols(&mut br, src.len(), 6, counts.len())?;
+
+        let mut br = BitReader::new(&src)?;
+        assert!(matches!(
+            DecodingTable::<64>::read_with_max_symbols(
+                &mut br,
+                src.len(),
+                6,
+                counts.len() - 1
+            ),
+            Err(Error::TooManySymbols)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_accuracy_log_ceiling() -> Result<(), Error> {
+        let table = crate::EncodingTable::from_counts(&counts_for(15), 15)?;
+        let mut src = Vec::new();
+        table.write_distribution(&mut src);
+
+        let mut br = BitReader::new(&src)?;
+        let (table, _) = DynDecodingTable::read(&mut br, src.len(), 15)?;
+        assert_eq!(table.table().len(), 1 << 15);
+
+        let mut br = BitReader::new(&src)?;
+        assert!(matches!(
+            DynDecodingTable::read(&mut br, src.len(), 12),
+            Err(Error::AccuracyLogMismatch(12, 15))
+        ));
+
+        Ok(())
+    }
+
+    fn counts_for(accuracy_log: u8) -> Vec<i16> {
+        let mut counts = vec![-1, -1, 1, 2, 3];
+        let used = 2 + 1 + 2 + 3;
+        let rest = ((1i32 << accuracy_log) - used) as i16;
+        counts.extend([rest / 2, rest - rest / 2]);
+        counts
+    }
+
+    #[test]
+    fn test_dyn_table_sizes() -> Result<(), Error> {
+        for accuracy_log in ACCURACY_LOG_RANGE {
+            let counts = counts_for(accuracy_log);
+
+            let mut dist = NormalizedDistribution::<MAX_TABLE_SIZE>::from_predefined(
+                &counts,
+                accuracy_log,
+            )?;
+            let table = DynDecodingTable::from_distribution(&mut dist)?;
+
+            assert_eq!(table.table().len(), 1 << accuracy_log);
+            assert!(
+                table
+                    .table()
+                    .iter()
+                    .all(|e| (e.symbol as usize) < counts.len())
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dyn_table_matches_const_table() -> Result<(), Error> {
+        for accuracy_log in [5, 6, 9, 12] {
+            let counts = counts_for(accuracy_log);
+
+            let mut dist =
+                NormalizedDistribution::<4096>::from_predefined(&counts, accuracy_log)?;
+            let expected = DecodingTable::<4096>::from_distribution(&mut dist.clone())?;
+            let actual = DynDecodingTable::from_distribution(&mut dist)?;
+
+            assert_eq!(expected.table().len(), actual.table().len());
+            for (state, (e, a)) in expected.table().iter().zip(actual.table()).enumerate()
+            {
+                assert_eq!(e.symbol, a.symbol, "State {}: Symbol mismatch", state);
+                assert_eq!(e.n_bits, a.n_bits, "State {}: Bits mismatch", state);
+                assert_eq!(e.baseline, a.baseline, "State {}: Base mismatch", state);
+            }
+
+            let data = [0xA5, 0x3C, 0x96, 0x0F, 0xE1, 0x5A, 0xC3, 0x69, 0x01];
+            let mut r1 = ReverseBitReader::new(&data)?;
+            let mut r2 = ReverseBitReader::new(&data)?;
+
+            let mut dec1 = Decoder::new(&expected, &mut r1)?;
+            let mut dec2 = DynDecoder::new(&actual, &mut r2)?;
+
+            while dec1.bits_required() as usize <= r1.bits_remaining() {
+                assert_eq!(dec1.peek(), dec2.peek());
+                assert_eq!(dec1.bits_required(), dec2.bits_required());
+
+                dec1.update(&mut r1)?;
+                dec2.update(&mut r2)?;
+            }
+        }
+
+        Ok(())
+    }
+
     proptest! {
         #![proptest_config(ProptestConfig::with_cases(1000))]
 
@@ -548,9 +1109,7 @@ mod tests {
             }
 
             let diff = N as i16 - current_sum;
-            if diff > 0 {
-                final_counts[0] += diff;
-            } else if diff < 0 {
+            if diff != 0 {
                 final_counts[0] += diff;
             }
 
@@ -577,7 +1136,7 @@ mod tests {
                 symbol_state,
                 symbol_count: weights.len(),
                 has_low_prob: false,
-                accuracy_log: current_sum as u8
+                accuracy_log: N.trailing_zeros() as u8,
             };
 
             let _ = DecodingTable::<N>::from_distribution(&mut dist)?;
@@ -0,0 +1,333 @@
+use rzstd_io::{BitWriter, ReverseBitWriter};
+
+use crate::{
+    DynDecodingTable, Error, NormalizedDistribution,
+    decode::{ACCURACY_LOG_RANGE, MAX_SYMBOLS, MAX_TABLE_SIZE},
+};
+
+/// A decoder state reachable when encoding a given symbol, along with the
+/// range of next states it can transition into.
+#[derive(Debug, Clone, Copy)]
+struct Transition {
+    state: u16,
+    baseline: u16,
+    n_bits: u8,
+}
+
+/// The encoding side of an FSE table.
+///
+/// It is derived from the same [DynDecodingTable] a decoder would build from
+/// the distribution, so both sides agree on every state by construction.
+#[derive(Debug)]
+pub struct EncodingTable {
+    counts: Vec<i16>,
+    accuracy_log: u8,
+    // For each symbol, its states sorted by baseline. Together they cover
+    // every possible next state exactly once.
+    transitions: Vec<Vec<Transition>>,
+}
+
+impl EncodingTable {
+    pub fn from_counts(counts: &[i16], accuracy_log: u8) -> Result<Self, Error> {
+        if counts.len() > MAX_SYMBOLS {
+            return Err(Error::TooManySymbols);
+        }
+        if !ACCURACY_LOG_RANGE.contains(&accuracy_log) {
+            return Err(Error::InvalidAccuracyLog(accuracy_log));
+        }
+
+        let sum = counts.iter().map(|&c| (c as i32).abs()).sum::<i32>();
+        if sum != 1 << accuracy_log {
+            return Err(Error::SumMismatch((1 << accuracy_log) - sum));
+        }
+
+        let mut dist = NormalizedDistribution::<MAX_TABLE_SIZE>::from_predefined(
+            counts,
+            accuracy_log,
+        )?;
+        let table = DynDecodingTable::from_distribution(&mut dist)?;
+
+        let mut transitions = vec![Vec::new(); counts.len()];
+        for (state, entry) in table.table().iter().enumerate() {
+            transitions[entry.symbol as usize].push(Transition {
+                state: state as u16,
+                baseline: entry.baseline,
+                n_bits: entry.n_bits,
+            });
+        }
+
+        for t in &mut transitions {
+            t.sort_unstable_by_key(|t| t.baseline);
+        }
+
+        Ok(Self {
+            counts: counts.to_vec(),
+            accuracy_log,
+            transitions,
+        })
+    }
+
+    /// Scales a histogram of symbol occurrences to a distribution summing to
+    /// `1 << accuracy_log`, keeping every present symbol at a count of at
+    /// least 1.
+    pub fn normalize(histogram: &[u32], accuracy_log: u8) -> Result<Vec<i16>, Error> {
+        if !ACCURACY_LOG_RANGE.contains(&accuracy_log) {
+            return Err(Error::InvalidAccuracyLog(accuracy_log));
+        }
+
+        let table_size = 1i64 << accuracy_log;
+        let total = histogram.iter().map(|&c| c as i64).sum::<i64>();
+        let present = histogram.iter().filter(|&&c| c > 0).count() as i64;
+        if total == 0 || present > table_size || histogram.len() > MAX_SYMBOLS {
+            return Err(Error::TooManySymbols);
+        }
+
+        let mut counts = histogram
+            .iter()
+            .map(|&c| match c {
+                0 => 0,
+                c => (c as i64 * table_size / total).max(1),
+            })
+            .collect::<Vec<_>>();
+
+        // Rounding leaves the sum off by a few; settle the difference on the
+        // most frequent symbols, which it affects the least.
+        let mut diff = table_size - counts.iter().sum::<i64>();
+        while diff != 0 {
+            let largest = (0..counts.len())
+                .filter(|&i| diff > 0 || counts[i] > 1)
+                .max_by_key(|&i| (counts[i], std::cmp::Reverse(i)))
+                .ok_or(Error::SumMismatch(diff as i32))?;
+
+            let step = if diff > 0 {
+                diff
+            } else {
+                diff.max(1 - counts[largest])
+            };
+            counts[largest] += step;
+            diff -= step;
+        }
+
+        Ok(counts.into_iter().map(|c| c as i16).collect())
+    }
+
+    pub const fn accuracy_log(&self) -> u8 {
+        self.accuracy_log
+    }
+
+    /// Writes the distribution in the format read by
+    /// [NormalizedDistribution::read], padded to a whole number of bytes.
+    pub fn write_distribution(&self, dst: &mut Vec<u8>) {
+        let mut w = BitWriter::new();
+        w.write((self.accuracy_log - ACCURACY_LOG_RANGE.start()) as u64, 4);
+
+        let last = self.counts.iter().rposition(|&c| c != 0).unwrap_or(0);
+
+        let mut remaining = 1i32 << self.accuracy_log;
+        let mut symbol = 0;
+        while remaining > 0 && symbol <= last {
+            let prob = self.counts[symbol];
+            symbol += 1;
+
+            let max_val = remaining + 1;
+            let n_bits = (32 - max_val.leading_zeros()) as u8;
+            let mask = (1 << (n_bits - 1)) - 1;
+            let threshold = (1 << n_bits) - max_val - 1;
+
+            let val = prob as i32 + 1;
+            if val < threshold {
+                w.write(val as u64, n_bits - 1);
+            } else if val <= mask {
+                w.write(val as u64, n_bits);
+            } else {
+                w.write((val + threshold) as u64, n_bits);
+            }
+
+            remaining -= if prob == -1 { 1 } else { prob as i32 };
+
+            if prob == 0 {
+                let mut zeros = self.counts[symbol..=last]
+                    .iter()
+                    .take_while(|&&c| c == 0)
+                    .count();
+                symbol += zeros;
+
+                while zeros >= 3 {
+                    w.write(3, 2);
+                    zeros -= 3;
+                }
+                w.write(zeros as u64, 2);
+            }
+        }
+
+        dst.extend(w.finish());
+    }
+
+    /// Encodes `symbols` with two interleaved states into a reverse bitstream,
+    /// the layout used for compressed Huffman weights.
+    ///
+    /// The decoder alternates between the states, and stops right after the
+    /// update of one state runs out of bits, emitting the other state's symbol
+    /// last. To make that happen on the second to last symbol, it is encoded
+    /// from a state that needs at least one bit.
+    pub fn encode_interleaved(&self, symbols: &[u8]) -> Result<Vec<u8>, Error> {
+        let [.., second_last, last] = *symbols else {
+            return Err(Error::Corruption);
+        };
+
+        let mut states = [self.initial_state(second_last)?, self.initial_state(last)?];
+        if self.transitions[second_last as usize][states[0]].n_bits == 0 {
+            return Err(Error::Corruption);
+        }
+
+        let mut w = ReverseBitWriter::new();
+
+        // Chains are walked backwards: the state for symbols[i] is the one
+        // leading into the already chosen state for symbols[i + 2].
+        for (i, &symbol) in symbols[..symbols.len() - 2].iter().enumerate().rev() {
+            let chain = (symbols.len() - 2 - i) % 2;
+
+            let next = self.state(symbols[i + 2], states[chain]);
+            let (idx, t) = self.predecessor(symbol, next)?;
+
+            w.write((next - t.baseline) as u64, t.n_bits);
+            states[chain] = idx;
+        }
+
+        let parity = symbols.len() % 2;
+        let first = self.state(symbols[0], states[parity]);
+        let second = self.state(symbols[1], states[1 - parity]);
+
+        w.write(second as u64, self.accuracy_log);
+        w.write(first as u64, self.accuracy_log);
+
+        Ok(w.finish())
+    }
+
+    /// Picks the state with the most bits to read for `symbol`.
+    fn initial_state(&self, symbol: u8) -> Result<usize, Error> {
+        self.transitions
+            .get(symbol as usize)
+            .and_then(|t| (0..t.len()).max_by_key(|&i| t[i].n_bits))
+            .ok_or(Error::InvalidState)
+    }
+
+    fn state(&self, symbol: u8, idx: usize) -> u16 {
+        self.transitions[symbol as usize][idx].state
+    }
+
+    /// Finds the state of `symbol` whose transitions cover `next`.
+    fn predecessor(&self, symbol: u8, next: u16) -> Result<(usize, Transition), Error> {
+        let transitions = self
+            .transitions
+            .get(symbol as usize)
+            .ok_or(Error::InvalidState)?;
+
+        let idx = transitions
+            .partition_point(|t| t.baseline <= next)
+            .checked_sub(1)
+            .ok_or(Error::InvalidState)?;
+
+        let t = transitions[idx];
+        if (next - t.baseline) >> t.n_bits != 0 {
+            return Err(Error::InvalidState);
+        }
+
+        Ok((idx, t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rzstd_io::{BitReader, ReverseBitReader};
+
+    use super::*;
+    use crate::{Decoder, DecodingTable};
+
+    /// Mirrors the two-state loop Huff0 uses to read compressed weights.
+    fn decode_interleaved(
+        table: &DecodingTable<64>,
+        src: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let mut br = ReverseBitReader::new(src)?;
+        let mut decoders = [Decoder::new(table, &mut br)?, Decoder::new(table, &mut br)?];
+
+        let mut out = Vec::new();
+        let mut turn = 0;
+        while let Some(symbol) = decoders[turn].decode_symbol(&mut br)? {
+            out.push(symbol);
+            turn = 1 - turn;
+        }
+        out.push(decoders[turn].peek());
+        out.push(decoders[1 - turn].peek());
+
+        Ok(out)
+    }
+
+    #[test]
+    fn test_normalize() -> Result<(), Error> {
+        let counts = EncodingTable::normalize(&[10, 0, 1, 1, 20], 5)?;
+
+        assert_eq!(counts.iter().map(|&c| c as i32).sum::<i32>(), 32);
+        assert_eq!(counts[1], 0);
+        assert!(counts.iter().enumerate().all(|(i, &c)| i == 1 || c >= 1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_distribution_round_trip() -> Result<(), Error> {
+        let counts = [4, 3, 0, 0, 0, 0, 0, 0, 2, 2, 0, 1, -1, -1, 19, 31];
+        let table = EncodingTable::from_counts(&counts, 6)?;
+
+        let mut buf = Vec::new();
+        table.write_distribution(&mut buf);
+
+        let mut br = BitReader::new(&buf)?;
+        let dist = NormalizedDistribution::<64>::read(&mut br, 6)?;
+        assert_eq!(br.bytes_consumed(), buf.len());
+        assert_eq!(
+            dist,
+            NormalizedDistribution::<64>::from_predefined(&counts, 6)?
+        );
+
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn test_interleaved_round_trip(
+            histogram in proptest::collection::vec(0u32..50, 2..12),
+            symbols in proptest::collection::vec(any::<u8>(), 2..255),
+            accuracy_log in 5u8..=6,
+        ) {
+            let present = histogram
+                .iter()
+                .enumerate()
+                .filter(|&(_, &c)| c > 0)
+                .map(|(s, _)| s as u8)
+                .collect::<Vec<_>>();
+            prop_assume!(present.len() >= 2);
+
+            let symbols = symbols
+                .iter()
+                .map(|&s| present[s as usize % present.len()])
+                .collect::<Vec<_>>();
+
+            let counts = EncodingTable::normalize(&histogram, accuracy_log)?;
+            let table = EncodingTable::from_counts(&counts, accuracy_log)?;
+
+            let mut src = Vec::new();
+            table.write_distribution(&mut src);
+            let header_len = src.len();
+            src.extend(table.encode_interleaved(&symbols)?);
+
+            let mut br = BitReader::new(&src)?;
+            let (decoding, consumed) = DecodingTable::<64>::read(&mut br, src.len(), 6)?;
+            prop_assert_eq!(consumed, header_len);
+
+            prop_assert_eq!(decode_interleaved(&decoding, &src[header_len..])?, symbols);
+        }
+    }
+}
@@ -32,6 +32,13 @@ pub enum Error {
     )]
     SumMismatch(i32),
 
+    #[error("Invalid count: {0}")]
+    #[diagnostic(
+        code(rzstd::fse::invalid_count),
+        help("A normalized count must be -1 (low probability) or non-negative.")
+    )]
+    InvalidCount(i16),
+
     #[error("Spread overflow")]
     #[diagnostic(
         code(rzstd::fse::spread_overflow),
@@ -1,5 +1,7 @@
 mod decode;
+mod encode;
 mod errors;
 
 pub use decode::*;
+pub use encode::*;
 pub use errors::*;
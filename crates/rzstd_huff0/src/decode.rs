@@ -2,12 +2,12 @@ use rzstd_foundation::const_assert;
 
 use crate::errors::Error;
 
-const MAX_BITS: u8 = 11;
+pub(crate) const MAX_BITS: u8 = 11;
 const_assert!(MAX_BITS <= 11);
 
 const TABLE_SIZE: usize = 1 << MAX_BITS;
 
-const FSE_ACCURACY_LOG: u8 = 6;
+pub(crate) const FSE_ACCURACY_LOG: u8 = 6;
 const FSE_TABLE_SIZE: usize = 1 << FSE_ACCURACY_LOG;
 const_assert!(FSE_TABLE_SIZE == 64);
 
@@ -18,7 +18,7 @@ pub struct Decoder<'t, const N: usize = TABLE_SIZE> {
 
 impl<'t, const N: usize> Decoder<'t, N> {
     pub fn new(table: &'t DecodingTable<N>, r: &mut rzstd_io::ReverseBitReader) -> Self {
-        let state = r.read_padded(table.max_bits) as u64;
+        let state = r.read_padded(table.max_bits);
         Self { table, state }
     }
 
@@ -53,13 +53,23 @@ impl std::fmt::Debug for Entry {
 }
 
 #[repr(align(64))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DecodingTable<const N: usize = TABLE_SIZE> {
     entries: [Entry; N],
     n_entries: usize,
     max_bits: u8,
+
+    /// Kept out of line, only for inspection: the decode loop never touches
+    /// it.
+    weights: Box<[u8]>,
 }
-const_assert!(std::mem::size_of::<DecodingTable>() % 64 == 0);
+const_assert!(std::mem::size_of::<DecodingTable>().is_multiple_of(64));
+// As large as before the weights were kept: the entries, then one cache line
+// for everything else.
+const_assert!(
+    std::mem::size_of::<DecodingTable>()
+        == std::mem::size_of::<[Entry; TABLE_SIZE]>() + 64
+);
 
 impl<const N: usize> DecodingTable<N> {
     pub fn read(src: &[u8]) -> Result<(Self, usize), Error> {
@@ -95,7 +105,7 @@ impl<const N: usize> DecodingTable<N> {
         let mut bit_rank = [0u32; (MAX_BITS + 1) as usize];
 
         for &w in weights {
-            if w <= 0 {
+            if w == 0 {
                 continue;
             }
 
@@ -109,6 +119,10 @@ impl<const N: usize> DecodingTable<N> {
         }
 
         let max_bits = sum.ilog2() as u8 + 1;
+        if max_bits > MAX_BITS {
+            return Err(Error::TableLogTooLarge(max_bits, MAX_BITS));
+        }
+
         let target = 1 << max_bits;
         let remainder = target - sum;
 
@@ -141,7 +155,7 @@ impl<const N: usize> DecodingTable<N> {
             .chain(std::iter::once(&inferred_weight))
             .enumerate()
         {
-            if w <= 0 {
+            if w == 0 {
                 continue;
             }
 
@@ -164,6 +178,7 @@ impl<const N: usize> DecodingTable<N> {
             entries,
             n_entries: target as usize,
             max_bits,
+            weights: weights.into(),
         })
     }
 
@@ -177,7 +192,13 @@ impl<const N: usize> DecodingTable<N> {
         let src = &src[1..];
 
         if header >= 128 {
+            // 1..=128 weights, so at most 129 symbols with the inferred one,
+            // packed two per byte.
             let count = header - 127;
+            if src.len() < (count as usize).div_ceil(2) {
+                return Err(Error::Corruption);
+            }
+
             let consumed = Self::read_weights_direct(src, out, count)?;
             Ok((count as usize, consumed + 1))
         } else {
@@ -191,46 +212,19 @@ impl<const N: usize> DecodingTable<N> {
         out: &mut [u8; 256],
         count: u8,
     ) -> Result<usize, Error> {
-        assert!(count <= 128);
+        debug_assert!(count <= 128);
         let count = count as usize;
 
         let mut r = rzstd_io::BitReader::new(src)?;
 
-        let mut idx = 0usize;
-        let mut remaining_bytes = (count + 1) / 2;
-
-        while remaining_bytes >= 7 && idx + 14 <= count {
-            assert!(idx + count <= out.len());
+        let mut packed = [0u8; 64];
+        let packed = &mut packed[..count.div_ceil(2)];
+        r.read_bytes(packed)?;
 
-            let chunk = r.read(56)?;
-            let dst = &mut out[idx..][..14];
-
-            for i in 0..7 {
-                let byte = (chunk >> (i * 8)) as u8;
-                dst[2 * i] = byte >> 4;
-                dst[2 * i + 1] = byte & 0xF;
-            }
-
-            idx += 14;
-            remaining_bytes -= 7;
-        }
-
-        if remaining_bytes == 0 {
-            return Ok(r.bytes_consumed());
-        }
-
-        let chunk = r.read((remaining_bytes * 8) as u8)?;
-
-        for i in 0..remaining_bytes {
-            let byte = (chunk >> (i * 8)) as u8;
-            if idx < count {
-                out[idx] = byte >> 4;
-                idx += 1;
-            }
-
-            if idx < count {
-                out[idx] = byte & 0xF;
-                idx += 1;
+        for (i, &byte) in packed.iter().enumerate() {
+            out[2 * i] = byte >> 4;
+            if 2 * i + 1 < count {
+                out[2 * i + 1] = byte & 0xF;
             }
         }
 
@@ -251,48 +245,44 @@ impl<const N: usize> DecodingTable<N> {
         }
 
         let mut table_reader = rzstd_io::BitReader::new(src)?;
-        let table = rzstd_fse::DecodingTable::<FSE_TABLE_SIZE>::read(
+        let (table, consumed) = rzstd_fse::DecodingTable::<FSE_TABLE_SIZE>::read(
             &mut table_reader,
             compressed_size,
+            FSE_ACCURACY_LOG,
         )?;
-        let mut br = rzstd_io::ReverseBitReader::new(
-            &src[table_reader.bytes_consumed()..compressed_size],
-        )?;
+        let mut br = rzstd_io::ReverseBitReader::new(&src[consumed..compressed_size])?;
 
-        let mut dec1 = rzstd_fse::Decoder::new(&table, &mut br)?;
-        let mut dec2 = rzstd_fse::Decoder::new(&table, &mut br)?;
+        let mut decoders = [
+            rzstd_fse::Decoder::new(&table, &mut br)?,
+            rzstd_fse::Decoder::new(&table, &mut br)?,
+        ];
 
+        // The states take turns until one runs out of bits, its symbol then
+        // followed by the other's to end the weights.
         let mut idx = 0;
-        while idx < out.len() {
-            out[idx] = dec1.peek();
+        let mut turn = 0;
+        while let Some(weight) = decoders[turn].decode_symbol(&mut br)? {
+            *out.get_mut(idx).ok_or(Error::Corruption)? = weight;
             idx += 1;
-
-            if dec1.bits_required() as usize > br.bits_remaining() {
-                out[idx] = dec2.peek();
-                idx += 1;
-                break;
-            }
-
-            dec1.update(&mut br)?;
-
-            out[idx] = dec2.peek();
-            idx += 1;
-
-            if dec2.bits_required() as usize > br.bits_remaining() {
-                out[idx] = dec1.peek();
-                idx += 1;
-                break;
-            }
-
-            dec2.update(&mut br)?;
+            turn = 1 - turn;
         }
 
-        Ok(idx)
+        let last = [decoders[turn].peek(), decoders[1 - turn].peek()];
+        out.get_mut(idx..idx + 2)
+            .ok_or(Error::Corruption)?
+            .copy_from_slice(&last);
+        Ok(idx + 2)
     }
 
     pub fn entries(&self) -> &[Entry] {
         &self.entries[..self.n_entries]
     }
+
+    /// The weights from the Huffman tree description that produced this
+    /// table, excluding the last symbol's weight, which is inferred.
+    pub fn weights(&self) -> &[u8] {
+        &self.weights
+    }
 }
 
 #[cfg(test)]
@@ -306,7 +296,7 @@ mod tests {
         let weights = [4, 3, 2, 0, 1];
         let table = DecodingTable::<64>::from_weights(&weights)?;
 
-        let data = [0x80, 0x0D];
+        let data = [0x01, 0x0D];
         let mut reader = rzstd_io::ReverseBitReader::new(&data)?;
         let mut decoder = Decoder::new(&table, &mut reader);
 
@@ -345,6 +335,29 @@ mod tests {
         assert!(DecodingTable::<256>::from_weights(&weights_bad).is_err());
     }
 
+    #[test]
+    fn test_table_log_too_large() {
+        // 2^10 + 2^10 leaves a 2^11 remainder, for a 12-bit table.
+        let weights = [11, 11];
+        assert!(matches!(
+            DecodingTable::<256>::from_weights(&weights),
+            Err(Error::TableLogTooLarge(12, MAX_BITS))
+        ));
+    }
+
+    #[test]
+    fn test_rfc_example_weights() -> Result<(), Error> {
+        let weights = [4, 3, 2, 0, 1];
+        let table = DecodingTable::<64>::from_weights(&weights)?;
+        assert_eq!(table.weights(), &weights);
+
+        let data = [129, 0x43];
+        let (table, _) = DecodingTable::<64>::read(&data)?;
+        assert_eq!(table.weights(), &[4, 3]);
+
+        Ok(())
+    }
+
     #[test]
     fn test_rfc_example() {
         let weights = [4, 3, 2, 0, 1];
@@ -363,6 +376,25 @@ mod tests {
         assert_eq!(table.max_bits, 4);
     }
 
+    #[test]
+    fn test_direct_weights_truncated() {
+        // Claims 5 weights (3 bytes) but only carries 2.
+        let data = [132, 0x43, 0x20];
+        assert!(matches!(
+            DecodingTable::<64>::read(&data),
+            Err(Error::Corruption)
+        ));
+
+        // Claims the maximum 128 weights (64 bytes) with none present.
+        assert!(matches!(
+            DecodingTable::<2048>::read(&[255]),
+            Err(Error::Corruption)
+        ));
+
+        let data = [132, 0x43, 0x20, 0x10];
+        assert!(DecodingTable::<64>::read(&data).is_ok());
+    }
+
     #[test]
     fn test_inferred_weight_boundaries() {
         let w1 = [1, 1, 1];
@@ -391,7 +423,7 @@ mod tests {
         ) {
              let header = 127 + count;
              let mut buf = vec![header];
-             let needed = (count as usize + 1) / 2;
+             let needed = (count as usize).div_ceil(2);
 
              let mut payload = payload;
              if payload.len() > needed {
@@ -402,6 +434,29 @@ mod tests {
              let _ = DecodingTable::<2048>::read(&buf);
         }
 
+        #[test]
+        fn test_direct_weights_match_nibbles(
+            header in 128u8..=255,
+            payload in proptest::collection::vec(any::<u8>(), 64),
+        ) {
+            let count = header as usize - 127;
+            let src = [&[header][..], &payload].concat();
+
+            let mut weights = [0u8; 256];
+            let (n_weights, consumed) = DecodingTable::<2048>::read_weights(&src, &mut weights)?;
+            prop_assert_eq!(n_weights, count);
+            prop_assert_eq!(consumed, 1 + count.div_ceil(2));
+
+            let expected = payload
+                .iter()
+                .flat_map(|&byte| [byte >> 4, byte & 0xF])
+                .take(count)
+                .collect::<Vec<_>>();
+            prop_assert_eq!(&weights[..count], expected.as_slice());
+            // An odd count leaves the final low nibble unread.
+            prop_assert!(weights[count..].iter().all(|&w| w == 0));
+        }
+
         #[test]
         fn test_table_consistency(
             weights in prop::collection::vec(0..=11u8, 1..255)
@@ -415,14 +470,14 @@ mod tests {
                 let target = sum.next_power_of_two();
                 let remainder = target - sum;
 
-                if remainder.is_power_of_two() {
-                    if let Ok(table) = DecodingTable::<2048>::from_weights(&weights) {
-                        assert!(table.max_bits <= 11);
+                if remainder.is_power_of_two()
+                    && let Ok(table) = DecodingTable::<2048>::from_weights(&weights)
+                {
+                    assert!(table.max_bits <= 11);
 
-                        let table_size = 1 << table.max_bits;
-                        for i in 0..table_size {
-                            assert!(table.entries[i].n_bits > 0, "Empty slot at index {}", i);
-                        }
+                    let table_size = 1 << table.max_bits;
+                    for i in 0..table_size {
+                        assert!(table.entries[i].n_bits > 0, "Empty slot at index {}", i);
                     }
                 }
             }
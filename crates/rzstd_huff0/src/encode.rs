@@ -0,0 +1,405 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use rzstd_io::ReverseBitWriter;
+
+use crate::{
+    Error,
+    decode::{FSE_ACCURACY_LOG, MAX_BITS},
+};
+
+/// Tree descriptions with more explicit weights than this can't be stored
+/// directly, one nibble per weight.
+const MAX_DIRECT_WEIGHTS: usize = 128;
+
+/// Compressed tree descriptions declare their size in a single header byte
+/// below 128.
+const MAX_COMPRESSED_WEIGHTS_SIZE: usize = 127;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Code {
+    value: u16,
+    n_bits: u8,
+}
+
+/// The encoding side of a Huffman table: a canonical prefix code for every
+/// symbol, matching what [crate::DecodingTable] rebuilds from the same
+/// weights.
+#[derive(Debug)]
+pub struct EncodingTable {
+    codes: [Code; 256],
+    max_bits: u8,
+
+    // The last symbol's weight is left out, the decoder infers it.
+    weights: Vec<u8>,
+}
+
+impl EncodingTable {
+    /// Builds a length-limited Huffman code for the symbols occurring in
+    /// `histogram`. At least two distinct symbols are required, a single
+    /// repeated byte is better stored as RLE literals.
+    pub fn from_histogram(histogram: &[u32; 256]) -> Result<Self, Error> {
+        let lengths = code_lengths(histogram)?;
+        let max_bits = lengths.iter().copied().max().unwrap_or(0);
+
+        let last = lengths.iter().rposition(|&l| l > 0).unwrap_or(0);
+        let weights = lengths[..last]
+            .iter()
+            .map(|&l| if l == 0 { 0 } else { max_bits + 1 - l })
+            .collect::<Vec<_>>();
+
+        Self::from_weights(&weights)
+    }
+
+    /// Builds the table from the explicit weights of a tree description, as
+    /// found in the header of compressed literals.
+    pub fn from_weights(weights: &[u8]) -> Result<Self, Error> {
+        if weights.len() > 255 {
+            return Err(Error::Corruption);
+        }
+
+        let mut sum = 0u32;
+        let mut bit_rank = [0u32; (MAX_BITS + 1) as usize];
+        for &w in weights {
+            if w > MAX_BITS {
+                return Err(Error::WeightTooLarge(w, MAX_BITS));
+            }
+
+            if w > 0 {
+                sum += 1 << (w - 1);
+                bit_rank[w as usize] += 1;
+            }
+        }
+
+        if sum == 0 {
+            return Err(Error::ZeroWeightSum);
+        }
+
+        let max_bits = sum.ilog2() as u8 + 1;
+        if max_bits > MAX_BITS {
+            return Err(Error::TableLogTooLarge(max_bits, MAX_BITS));
+        }
+
+        let remainder = (1 << max_bits) - sum;
+        if !remainder.is_power_of_two() {
+            return Err(Error::InvalidInferredWeight(remainder));
+        }
+
+        let inferred_weight = remainder.ilog2() as u8 + 1;
+        bit_rank[inferred_weight as usize] += 1;
+
+        // Same canonical assignment as the decoder: lowest weights (longest
+        // codes) first, then by symbol.
+        let mut next_code = [0u32; (MAX_BITS + 1) as usize];
+        let mut curr = 0u32;
+        for w in 1..=max_bits as usize {
+            next_code[w] = curr;
+            curr += bit_rank[w] << (w - 1);
+        }
+
+        let mut codes = [Code::default(); 256];
+        for (sym, &w) in weights
+            .iter()
+            .chain(std::iter::once(&inferred_weight))
+            .enumerate()
+        {
+            if w == 0 {
+                continue;
+            }
+
+            codes[sym] = Code {
+                value: (next_code[w as usize] >> (w - 1)) as u16,
+                n_bits: max_bits + 1 - w,
+            };
+            next_code[w as usize] += 1 << (w - 1);
+        }
+
+        Ok(Self {
+            codes,
+            max_bits,
+            weights: weights.to_vec(),
+        })
+    }
+
+    /// The explicit weights of the tree description, excluding the last
+    /// symbol's.
+    pub fn weights(&self) -> &[u8] {
+        &self.weights
+    }
+
+    pub fn max_bits(&self) -> u8 {
+        self.max_bits
+    }
+
+    /// Writes the tree description, picking whichever of the direct and the
+    /// FSE-compressed representations is available and smaller.
+    pub fn write_description(&self, dst: &mut Vec<u8>) -> Result<(), Error> {
+        let compressed = self.compress_weights();
+        let direct_fits = self.weights.len() <= MAX_DIRECT_WEIGHTS;
+
+        match compressed {
+            Some(compressed)
+                if !direct_fits || compressed.len() < self.weights.len().div_ceil(2) =>
+            {
+                dst.push(compressed.len() as u8);
+                dst.extend(compressed);
+            }
+            _ if direct_fits => {
+                dst.push(127 + self.weights.len() as u8);
+                dst.extend(
+                    self.weights
+                        .chunks(2)
+                        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0)),
+                );
+            }
+            _ => return Err(Error::TableOverflow),
+        }
+
+        Ok(())
+    }
+
+    /// The weights as an FSE-compressed tree description (without the header
+    /// byte), if they fit in one.
+    fn compress_weights(&self) -> Option<Vec<u8>> {
+        if self.weights.len() < 2 {
+            return None;
+        }
+
+        let mut histogram = [0u32; (MAX_BITS + 1) as usize];
+        for &w in &self.weights {
+            histogram[w as usize] += 1;
+        }
+
+        // A distribution made of a single symbol can't end the interleaved
+        // stream, so make room for an unused one.
+        if histogram.iter().filter(|&&c| c > 0).count() == 1 {
+            let unused = histogram.iter().position(|&c| c == 0)?;
+            histogram[unused] = 1;
+        }
+
+        let counts =
+            rzstd_fse::EncodingTable::normalize(&histogram, FSE_ACCURACY_LOG).ok()?;
+        let table =
+            rzstd_fse::EncodingTable::from_counts(&counts, FSE_ACCURACY_LOG).ok()?;
+
+        let mut out = Vec::new();
+        table.write_distribution(&mut out);
+        out.extend(table.encode_interleaved(&self.weights).ok()?);
+
+        (out.len() <= MAX_COMPRESSED_WEIGHTS_SIZE).then_some(out)
+    }
+
+    /// Appends `literals` as a single Huffman stream, readable by
+    /// [crate::Decoder] front to back.
+    pub fn encode(&self, literals: &[u8], dst: &mut Vec<u8>) -> Result<(), Error> {
+        let mut w = ReverseBitWriter::new();
+
+        for &lit in literals.iter().rev() {
+            let code = self.codes[lit as usize];
+            if code.n_bits == 0 {
+                return Err(Error::MissingSymbol(lit));
+            }
+
+            w.write(code.value as u64, code.n_bits);
+        }
+
+        dst.extend(w.finish());
+        Ok(())
+    }
+}
+
+/// Compresses `literals` into a tree description followed by a single
+/// Huffman stream.
+pub fn compress(literals: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut histogram = [0u32; 256];
+    for &lit in literals {
+        histogram[lit as usize] += 1;
+    }
+
+    let table = EncodingTable::from_histogram(&histogram)?;
+
+    let mut dst = Vec::new();
+    table.write_description(&mut dst)?;
+    table.encode(literals, &mut dst)?;
+    Ok(dst)
+}
+
+/// Huffman code lengths for every symbol, capped at [MAX_BITS] and forming a
+/// complete prefix code.
+fn code_lengths(histogram: &[u32; 256]) -> Result<[u8; 256], Error> {
+    let present = (0..256).filter(|&s| histogram[s] > 0).collect::<Vec<_>>();
+    if present.len() < 2 {
+        return Err(Error::NotEnoughSymbols(present.len()));
+    }
+
+    // Leaves take the ids 0..256, internal nodes are appended after them.
+    let mut parent = vec![0usize; 2 * 256];
+    let mut heap = present
+        .iter()
+        .map(|&s| Reverse((histogram[s] as u64, s)))
+        .collect::<BinaryHeap<_>>();
+
+    let mut next_id = 256;
+    while let (Some(Reverse((a, a_id))), Some(Reverse((b, b_id)))) =
+        (heap.pop(), heap.pop())
+    {
+        parent[a_id] = next_id;
+        parent[b_id] = next_id;
+        heap.push(Reverse((a + b, next_id)));
+        next_id += 1;
+    }
+    let root = next_id - 1;
+
+    let mut lengths = [0u8; 256];
+    for &s in &present {
+        let mut depth = 0;
+        let mut node = s;
+        while node != root {
+            node = parent[node];
+            depth += 1;
+        }
+
+        lengths[s] = depth.min(MAX_BITS as usize) as u8;
+    }
+
+    // Clamping made the code overfull; lengthen the cheapest symbols until it
+    // fits, then shorten the longest ones to fill any slack left behind.
+    let capacity = 1u32 << MAX_BITS;
+    let kraft = |lengths: &[u8; 256]| {
+        present
+            .iter()
+            .map(|&s| 1u32 << (MAX_BITS - lengths[s]))
+            .sum::<u32>()
+    };
+
+    let mut by_cost = present.clone();
+    by_cost.sort_by_key(|&s| (histogram[s], Reverse(s)));
+
+    while kraft(&lengths) > capacity {
+        let s = by_cost
+            .iter()
+            .rev()
+            .copied()
+            .filter(|&s| lengths[s] < MAX_BITS)
+            .max_by_key(|&s| lengths[s])
+            .ok_or(Error::TableOverflow)?;
+        lengths[s] += 1;
+    }
+
+    while kraft(&lengths) < capacity {
+        let slack = capacity - kraft(&lengths);
+        let s = by_cost
+            .iter()
+            .copied()
+            .filter(|&s| lengths[s] > 1 && 1 << (MAX_BITS - lengths[s]) <= slack)
+            .max_by_key(|&s| lengths[s])
+            .ok_or(Error::TableUnderflow)?;
+        lengths[s] -= 1;
+    }
+
+    Ok(lengths)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{Decoder, DecodingTable};
+
+    fn decompress(src: &[u8], len: usize) -> Result<Vec<u8>, Error> {
+        let (table, consumed) = DecodingTable::<2048>::read(src)?;
+
+        let mut r = rzstd_io::ReverseBitReader::new(&src[consumed..])?;
+        let mut decoder = Decoder::new(&table, &mut r);
+        let out = (0..len).map(|_| decoder.decode(&mut r)).collect();
+
+        assert_eq!(r.bits_remaining(), 0);
+        Ok(out)
+    }
+
+    #[test]
+    fn test_rfc_example_encoding() -> Result<(), Error> {
+        // RFC 8878 4.2.1.3: the prefix codes of the weights 4, 3, 2, 0, 1,
+        // with the last symbol's weight of 1 inferred.
+        let table = EncodingTable::from_weights(&[4, 3, 2, 0, 1])?;
+        assert_eq!(table.max_bits(), 4);
+
+        let expected = [(0, 1, 1), (1, 2, 1), (2, 3, 1), (4, 4, 0), (5, 4, 1)];
+        for (sym, n_bits, value) in expected {
+            let code = table.codes[sym];
+            assert_eq!((code.n_bits, code.value), (n_bits, value), "symbol {sym}");
+        }
+        assert_eq!(table.codes[3].n_bits, 0);
+
+        // The same 4 symbols the decoder test reads back from [0x01, 0x0D].
+        let mut stream = Vec::new();
+        table.encode(&[0, 1, 4, 5], &mut stream)?;
+        assert_eq!(stream, [0x01, 0x0D]);
+
+        let mut description = Vec::new();
+        table.write_description(&mut description)?;
+        assert_eq!(description, [132, 0x43, 0x20, 0x10]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_symbol() {
+        assert!(matches!(
+            compress(&[7; 100]),
+            Err(Error::NotEnoughSymbols(1))
+        ));
+    }
+
+    #[test]
+    fn test_uniform_bytes() -> Result<(), Error> {
+        // 255 equal explicit weights: too many to store directly.
+        let literals = (0..=255).cycle().take(4096).collect::<Vec<u8>>();
+        let encoded = compress(&literals)?;
+
+        assert!(encoded[0] < 128, "expected an FSE-compressed description");
+        assert_eq!(decompress(&encoded, literals.len())?, literals);
+        Ok(())
+    }
+
+    #[test]
+    fn test_length_limit() -> Result<(), Error> {
+        // Fibonacci frequencies produce a maximally deep Huffman tree.
+        let mut histogram = [0u32; 256];
+        let (mut a, mut b) = (1, 1);
+        for h in histogram.iter_mut().take(20) {
+            *h = a;
+            (a, b) = (b, a + b);
+        }
+
+        let table = EncodingTable::from_histogram(&histogram)?;
+        assert_eq!(table.max_bits(), MAX_BITS);
+
+        let literals = (0..20u8).collect::<Vec<_>>();
+        let mut encoded = Vec::new();
+        table.write_description(&mut encoded)?;
+        table.encode(&literals, &mut encoded)?;
+
+        assert_eq!(decompress(&encoded, literals.len())?, literals);
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(
+            alphabet in proptest::collection::vec(any::<u8>(), 2..64),
+            literals in proptest::collection::vec(any::<prop::sample::Index>(), 1..2048),
+        ) {
+            let literals = literals
+                .iter()
+                .map(|i| alphabet[i.index(alphabet.len())])
+                .collect::<Vec<_>>();
+
+            let distinct = literals.iter().collect::<std::collections::HashSet<_>>().len();
+            prop_assume!(distinct >= 2);
+
+            let encoded = compress(&literals)?;
+            prop_assert_eq!(decompress(&encoded, literals.len())?, literals);
+        }
+    }
+}
@@ -61,6 +61,20 @@ pub enum Error {
     )]
     InvalidInferredWeight(u32),
 
+    #[error("Symbol {0} has no code in the Huffman table")]
+    #[diagnostic(
+        code(rzstd::huff0::missing_symbol),
+        help("The literals to encode contain a byte absent from the table's histogram.")
+    )]
+    MissingSymbol(u8),
+
+    #[error("Huffman coding needs at least 2 distinct symbols, got {0}")]
+    #[diagnostic(
+        code(rzstd::huff0::not_enough_symbols),
+        help("Literals made of a single repeated byte should be stored as RLE instead.")
+    )]
+    NotEnoughSymbols(usize),
+
     #[error("Decoding table entry overwrite at index {0}")]
     #[diagnostic(
         code(rzstd::huff0::entry_overwrite),
@@ -44,9 +44,14 @@ impl<'src> BitReader<'src> {
         Ok(())
     }
 
+    /// Reads the next `n_bits` bits.
+    ///
+    /// `n_bits` must be at most 56; this is only checked in debug builds, as
+    /// it sits in the hottest loops. Larger requests stay memory-safe but may
+    /// fail with [Error::NotEnoughBits] despite enough bits being left.
     #[inline(always)]
     pub fn read(&mut self, n_bits: u8) -> Result<u64, Error> {
-        assert!(n_bits <= 56);
+        debug_assert!(n_bits <= 56);
 
         if self.bit_count < n_bits {
             self.refill();
@@ -59,12 +64,49 @@ impl<'src> BitReader<'src> {
             }
         }
 
-        let ret = self.peek(n_bits);
-        self.buf >>= n_bits;
-        self.bit_count -= n_bits;
+        let ret = self.peek_unchecked(n_bits);
+        self.consume_unchecked(n_bits);
         Ok(ret)
     }
 
+    /// Fills `out` with the next `out.len()` bytes of the stream.
+    ///
+    /// When the reader sits on a byte boundary, the bytes are copied straight
+    /// out of the source instead of going through the bit buffer.
+    pub fn read_bytes(&mut self, out: &mut [u8]) -> Result<(), Error> {
+        if self.bits_remaining() < out.len() * 8 {
+            return Err(Error::NotEnoughBits {
+                requested: out.len() * 8,
+                remaining: self.bits_remaining(),
+            });
+        }
+
+        if !self.bit_count.is_multiple_of(8) {
+            for byte in out {
+                *byte = self.read(8)? as u8;
+            }
+            return Ok(());
+        }
+
+        let buffered = (self.bit_count as usize / 8).min(out.len());
+        let (head, tail) = out.split_at_mut(buffered);
+        for byte in head {
+            *byte = self.buf as u8;
+            self.consume_unchecked(8);
+        }
+
+        if !tail.is_empty() {
+            debug_assert_eq!(self.bit_count, 0);
+
+            let (bytes, rest) = self.src.split_at(tail.len());
+            tail.copy_from_slice(bytes);
+            self.src = rest;
+            self.index += tail.len();
+        }
+
+        Ok(())
+    }
+
     #[inline(always)]
     pub fn peek(&self, n_bits: u8) -> u64 {
         assert!(
@@ -73,7 +115,14 @@ impl<'src> BitReader<'src> {
             self.bit_count,
             self.src
         );
+        self.peek_unchecked(n_bits)
+    }
 
+    /// [BitReader::peek] for callers that made sure `n_bits` bits are
+    /// buffered.
+    #[inline(always)]
+    fn peek_unchecked(&self, n_bits: u8) -> u64 {
+        debug_assert!(n_bits <= self.bit_count);
         self.buf & ((1u64 << n_bits) - 1)
     }
 
@@ -109,7 +158,7 @@ impl<'src> BitReader<'src> {
             return self.refill_cold(to_read);
         }
 
-        assert_eq!(self.bit_count, 0);
+        debug_assert_eq!(self.bit_count, 0);
 
         let buf = {
             let bytes = self.src[..8]
@@ -218,6 +267,82 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_read_bytes_aligned() -> Result<(), Error> {
+        let data = (1..=20).collect::<Vec<u8>>();
+        let mut br = BitReader::new(&data)?;
+
+        let mut out = [0; 3];
+        br.read_bytes(&mut out)?;
+        assert_eq!(out, [1, 2, 3]);
+
+        // Drains the rest of the bit buffer, then copies from the source.
+        let mut out = [0; 12];
+        br.read_bytes(&mut out)?;
+        assert_eq!(out, [4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+
+        assert_eq!(br.read(8)?, 16);
+        assert_eq!(br.bytes_consumed(), 16);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_bytes_misaligned() -> Result<(), Error> {
+        let mut br = BitReader::new(&[0x12, 0x34, 0x56])?;
+
+        assert_eq!(br.read(4)?, 0x2);
+
+        let mut out = [0; 2];
+        br.read_bytes(&mut out)?;
+        assert_eq!(out, [0x41, 0x63]);
+        assert_eq!(br.read(4)?, 0x5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_bytes_not_enough_bits() -> Result<(), Error> {
+        let mut br = BitReader::new(&[0xAA, 0xBB])?;
+
+        let mut out = [0; 3];
+        assert!(matches!(
+            br.read_bytes(&mut out),
+            Err(Error::NotEnoughBits {
+                requested: 24,
+                remaining: 16
+            })
+        ));
+
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn test_fuzz_read_bytes(
+            src in proptest::collection::vec(any::<u8>(), 3..64),
+            skip in 0u8..=16,
+            len in 0usize..64,
+        ) {
+            let len = len.min((src.len() * 8 - skip as usize) / 8);
+
+            let mut expected = BitReader::new(&src)?;
+            let mut actual = BitReader::new(&src)?;
+            expected.read(skip)?;
+            actual.read(skip)?;
+
+            let expected_bytes = (0..len)
+                .map(|_| expected.read(8).map(|b| b as u8))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut actual_bytes = vec![0; len];
+            actual.read_bytes(&mut actual_bytes)?;
+
+            prop_assert_eq!(actual_bytes, expected_bytes);
+            prop_assert_eq!(actual.bits_remaining(), expected.bits_remaining());
+            prop_assert_eq!(actual.bytes_consumed(), expected.bytes_consumed());
+        }
+    }
+
     proptest! {
         #![proptest_config(ProptestConfig::with_cases(1000))]
 
@@ -244,7 +369,7 @@ mod tests {
                 let (chunk, rest) = remaining.split_at(n as usize);
                 let expected = pack_bits(chunk);
 
-                let actual = br.read(n as u8)?;
+                let actual = br.read(n)?;
                 prop_assert_eq!(actual, expected, "Mismatch reading {} bits", n);
 
                 remaining = rest;
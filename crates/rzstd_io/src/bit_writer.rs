@@ -0,0 +1,83 @@
+/// Appends bits LSB-first, as [crate::BitReader] reads them.
+#[derive(Debug, Default)]
+pub struct BitWriter {
+    dst: Vec<u8>,
+    buf: u64,
+    bit_count: u8,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the low `n_bits` of `value`.
+    #[inline(always)]
+    pub fn write(&mut self, value: u64, n_bits: u8) {
+        assert!(n_bits <= 56);
+        if n_bits == 0 {
+            return;
+        }
+
+        self.buf |= (value & (u64::MAX >> (64 - n_bits))) << self.bit_count;
+        self.bit_count += n_bits;
+
+        while self.bit_count >= 8 {
+            self.dst.push(self.buf as u8);
+            self.buf >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// The number of bits written so far.
+    pub fn bits_written(&self) -> usize {
+        self.dst.len() * 8 + self.bit_count as usize
+    }
+
+    /// Pads the last byte with zeros and returns the written bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.dst.push(self.buf as u8);
+        }
+        self.dst
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::BitReader;
+
+    #[test]
+    fn test_bit_order() {
+        let mut w = BitWriter::new();
+        w.write(0b101, 3);
+        w.write(0b11, 2);
+        w.write(0x1ff, 9);
+
+        assert_eq!(w.bits_written(), 14);
+        assert_eq!(w.finish(), [0b1111_1101, 0b0011_1111]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(
+            writes in proptest::collection::vec((any::<u64>(), 0u8..=56), 1..500)
+        ) {
+            let mut w = BitWriter::new();
+            for &(value, n_bits) in &writes {
+                w.write(value, n_bits);
+            }
+            let src = w.finish();
+            prop_assume!(!src.is_empty());
+
+            let mut br = BitReader::new(&src)?;
+            for (value, n_bits) in writes {
+                let mask = u64::MAX.checked_shr(64 - n_bits as u32).unwrap_or(0);
+                prop_assert_eq!(br.read(n_bits)?, value & mask);
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+// The derived `Diagnostic` impl destructures struct-like variants, which trips
+// a false `unused_assignments` positive on rustc 1.92.
+#![allow(unused_assignments)]
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum Error {
+    #[error("Stream is empty")]
+    #[diagnostic(
+        code(rzstd::io::empty_stream),
+        help("The input stream ended unexpectedly. Verify the input data is complete.")
+    )]
+    EmptyStream,
+
+    #[error("Stream end sentinel is missing")]
+    #[diagnostic(
+        code(rzstd::io::missing_sentinel),
+        help("The stream should end with a sentinel bit/byte but it was not found.")
+    )]
+    MissingSentinel,
+
+    #[error("{0} is missing its end sentinel")]
+    #[diagnostic(
+        code(rzstd::io::missing_sentinel),
+        help("The stream should end with a sentinel bit/byte but it was not found.")
+    )]
+    MissingSentinelIn(&'static str),
+
+    #[error(
+        "Not enough bits in stream. Requested: {requested:?}, Remaining: {remaining:?}"
+    )]
+    #[diagnostic(
+        code(rzstd::io::not_enough_bits),
+        help("Attempted to read more bits than are available in the stream.")
+    )]
+    NotEnoughBits { requested: usize, remaining: usize },
+
+    #[error(transparent)]
+    #[diagnostic(code(rzstd::io::io_error))]
+    IO(#[from] std::io::Error),
+}
@@ -27,3 +27,230 @@ impl<T: std::io::Read> ReadU32 for T {
         Ok(u32::from_le_bytes(buf))
     }
 }
+
+/// Readers backed by memory, exposing the bytes they haven't handed out yet so
+/// that they can be copied from in place.
+///
+/// [Reader] is implemented for every [std::io::Read], so this is a trait of
+/// its own rather than a method readers could opt into overriding.
+pub trait RemainingSlice {
+    fn remaining_slice(&self) -> &[u8];
+}
+
+impl RemainingSlice for &[u8] {
+    #[inline(always)]
+    fn remaining_slice(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<T: AsRef<[u8]>> RemainingSlice for std::io::Cursor<T> {
+    #[inline(always)]
+    fn remaining_slice(&self) -> &[u8] {
+        let buf = self.get_ref().as_ref();
+        let start = self.position().min(buf.len() as u64) as usize;
+        &buf[start..]
+    }
+}
+
+/// Wraps a reader, keeping track of how many bytes were pulled through it.
+#[derive(Debug)]
+pub struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// Total number of bytes read so far.
+    #[inline(always)]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: RemainingSlice> RemainingSlice for CountingReader<R> {
+    #[inline(always)]
+    fn remaining_slice(&self) -> &[u8] {
+        self.inner.remaining_slice()
+    }
+}
+
+impl<R: std::io::Read> std::io::Read for CountingReader<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// Wraps a reader, bounding reads to a fixed number of bytes.
+///
+/// Unlike [std::io::Take], reading past the bound is an error rather than an
+/// end of stream, so a region claiming fewer bytes than its contents need is
+/// caught where it overflows.
+#[derive(Debug)]
+pub struct TakeExact<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R> TakeExact<R> {
+    pub fn new(inner: R, limit: u64) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+        }
+    }
+
+    /// Bytes left before reads start failing.
+    #[inline(always)]
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    pub fn set_limit(&mut self, limit: u64) {
+        self.remaining = limit;
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: RemainingSlice> RemainingSlice for TakeExact<R> {
+    /// The bytes left underneath, cut at the bound.
+    #[inline(always)]
+    fn remaining_slice(&self) -> &[u8] {
+        let slice = self.inner.remaining_slice();
+        let len = slice
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        &slice[..len]
+    }
+}
+
+impl<R: std::io::Read> std::io::Read for TakeExact<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                crate::Error::NotEnoughBits {
+                    requested: buf.len() * 8,
+                    remaining: 0,
+                },
+            ));
+        }
+
+        let len = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let n = self.inner.read(&mut buf[..len])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn test_counting_reader() -> std::io::Result<()> {
+        let data = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+        let mut r = CountingReader::new(&data[..]);
+
+        assert_eq!(r.read_u8()?, 0x11);
+        assert_eq!(r.count(), 1);
+
+        assert_eq!(r.read_u32()?, 0x5544_3322);
+        assert_eq!(r.count(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_take_exact() -> std::io::Result<()> {
+        let data = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+        let mut r = TakeExact::new(&data[..], 4);
+
+        assert_eq!(r.read_u8()?, 0x11);
+        assert_eq!(r.remaining(), 3);
+
+        // Reads stop at the bound, even with more data underneath.
+        let mut buf = [0; 4];
+        assert_eq!(r.read(&mut buf)?, 3);
+        assert_eq!(buf[..3], [0x22, 0x33, 0x44]);
+
+        let err = r.read_u8().expect_err("read past the bound");
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        r.set_limit(2);
+        assert_eq!(r.read_u8()?, 0x55);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remaining_slice() -> std::io::Result<()> {
+        let data = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+
+        let mut cursor = std::io::Cursor::new(&data[..]);
+        assert_eq!(cursor.remaining_slice(), data);
+        cursor.read_u8()?;
+        assert_eq!(cursor.remaining_slice(), [0x22, 0x33, 0x44, 0x55, 0x66]);
+        cursor.read_u32()?;
+        assert_eq!(cursor.remaining_slice(), [0x66]);
+        cursor.set_position(10);
+        assert_eq!(cursor.remaining_slice(), []);
+
+        let mut r = TakeExact::new(CountingReader::new(&data[..]), 4);
+        r.read_u8()?;
+        assert_eq!(r.remaining_slice(), [0x22, 0x33, 0x44]);
+        r.set_limit(u64::MAX);
+        assert_eq!(r.remaining_slice(), [0x22, 0x33, 0x44, 0x55, 0x66]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_take_exact_short_source() {
+        // The bound claims more bytes than the source holds.
+        let mut r = TakeExact::new(&[0x11, 0x22][..], 4);
+
+        let mut buf = [0; 4];
+        let err = r.read_exact(&mut buf).expect_err("source is too short");
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
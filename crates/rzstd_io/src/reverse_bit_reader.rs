@@ -32,6 +32,15 @@ impl<'src> ReverseBitReader<'src> {
         })
     }
 
+    /// Like [ReverseBitReader::new], but names the stream in
+    /// [Error::MissingSentinelIn] when its last byte is zero.
+    pub fn new_named(src: &'src [u8], label: &'static str) -> Result<Self, Error> {
+        match Self::new(src) {
+            Err(Error::MissingSentinel) => Err(Error::MissingSentinelIn(label)),
+            res => res,
+        }
+    }
+
     #[inline(always)]
     pub fn bit_count(&self) -> u8 {
         self.bit_count
@@ -51,27 +60,35 @@ impl<'src> ReverseBitReader<'src> {
         Ok(())
     }
 
+    /// Reads the next `n_bits` bits.
+    ///
+    /// `n_bits` must be at most 56, which every caller's table limits keep it
+    /// well under; this is only checked in debug builds, as it sits in the
+    /// hottest loops. Larger requests stay memory-safe but may fail with
+    /// [Error::NotEnoughBits] despite enough bits being left.
     #[inline(always)]
     pub fn read(&mut self, n_bits: u8) -> Result<u64, Error> {
-        assert!(n_bits <= 56);
+        debug_assert!(n_bits <= 56);
 
         self.ensure_bits(n_bits)?;
-        let ret = self.peek(n_bits);
+        let ret = self.peek_unchecked(n_bits);
         self.consume_unchecked(n_bits);
 
         Ok(ret)
     }
 
+    /// Like [ReverseBitReader::read], under the same contract on `n_bits`,
+    /// but pads with zeroes past the start of the stream instead of failing.
     #[inline(always)]
     pub fn read_padded(&mut self, n_bits: u8) -> u64 {
-        assert!(n_bits <= 56);
+        debug_assert!(n_bits <= 56);
 
         if self.bit_count < n_bits {
             self.refill();
         }
 
         let to_read = n_bits.min(self.bit_count);
-        let ret = self.peek(to_read);
+        let ret = self.peek_unchecked(to_read);
         self.consume_unchecked(to_read);
 
         ret << (n_bits - to_read)
@@ -89,6 +106,17 @@ impl<'src> ReverseBitReader<'src> {
         }
 
         assert!(n_bits <= self.bit_count);
+        self.peek_unchecked(n_bits)
+    }
+
+    /// [ReverseBitReader::peek] for callers that made sure `n_bits` bits are
+    /// buffered.
+    #[inline(always)]
+    fn peek_unchecked(&self, n_bits: u8) -> u64 {
+        debug_assert!(n_bits <= self.bit_count);
+        if n_bits == 0 {
+            return 0;
+        }
 
         let shift = self.bit_count - n_bits;
         (self.buf >> shift) & ((1u64 << n_bits) - 1)
@@ -109,7 +137,7 @@ impl<'src> ReverseBitReader<'src> {
     #[inline(always)]
     #[cold]
     fn refill(&mut self) {
-        assert!(self.bit_count < 64);
+        debug_assert!(self.bit_count < 64);
 
         let count = ((64 - self.bit_count) / 8) as usize;
         if count == 0 {
@@ -121,7 +149,7 @@ impl<'src> ReverseBitReader<'src> {
             return self.refill_cold(to_read);
         }
 
-        assert_eq!(self.bit_count, 0);
+        debug_assert_eq!(self.bit_count, 0);
 
         let start = self.src.len() - 8;
         let buf = {
@@ -199,7 +227,6 @@ mod tests {
     use proptest::prelude::*;
 
     use super::*;
-    use crate::tests::*;
 
     #[test]
     fn test_sentinel_and_bit_order() -> Result<(), Error> {
@@ -207,10 +234,10 @@ mod tests {
 
         let mut br = ReverseBitReader::new(&data)?;
 
-        assert_eq!(br.read(1)?, 1, "Bit 0 should be 1");
-        assert_eq!(br.read(1)?, 0, "Bit 1 should be 0");
-        assert_eq!(br.read(1)?, 1, "Bit 2 should be 1");
         assert_eq!(br.read(1)?, 1, "Bit 3 should be 1");
+        assert_eq!(br.read(1)?, 1, "Bit 2 should be 1");
+        assert_eq!(br.read(1)?, 0, "Bit 1 should be 0");
+        assert_eq!(br.read(1)?, 1, "Bit 0 should be 1");
 
         assert!(matches!(br.read(1), Err(Error::NotEnoughBits { .. })));
 
@@ -294,7 +321,7 @@ mod tests {
               let (chunk, rest) = remaining.split_at(n as usize);
 
               let expected = pack_bits(chunk);
-              let actual = br.read(n as u8)?;
+              let actual = br.read(n)?;
 
               prop_assert_eq!(actual, expected,
                   "Mismatch reading {} bits ({} bits remaining)", n, remaining.len());
@@ -309,6 +336,11 @@ mod tests {
         }
     }
 
+    /// Bits are consumed from the end of the stream, most significant first.
+    fn pack_bits(chunk: &[bool]) -> u64 {
+        chunk.iter().fold(0, |acc, &b| (acc << 1) | b as u64)
+    }
+
     fn encode_bits(bits: &[bool]) -> Vec<u8> {
         let rem = bits.len() % 8;
         let (head, tail) = bits.split_at(rem);
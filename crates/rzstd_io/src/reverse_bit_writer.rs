@@ -0,0 +1,66 @@
+use crate::BitWriter;
+
+/// Appends bits so that [crate::ReverseBitReader] reads them back last written
+/// first, closing the stream with the sentinel bit.
+#[derive(Debug, Default)]
+pub struct ReverseBitWriter(BitWriter);
+
+impl ReverseBitWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the low `n_bits` of `value`.
+    #[inline(always)]
+    pub fn write(&mut self, value: u64, n_bits: u8) {
+        self.0.write(value, n_bits);
+    }
+
+    /// The number of bits written so far, not counting the sentinel.
+    pub fn bits_written(&self) -> usize {
+        self.0.bits_written()
+    }
+
+    /// Appends the sentinel bit and returns the written bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.0.write(1, 1);
+        self.0.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::ReverseBitReader;
+
+    #[test]
+    fn test_sentinel() {
+        assert_eq!(ReverseBitWriter::new().finish(), [0x01]);
+
+        let mut w = ReverseBitWriter::new();
+        w.write(0b101, 3);
+        assert_eq!(w.finish(), [0b1101]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(
+            writes in proptest::collection::vec((any::<u64>(), 0u8..=56), 0..500)
+        ) {
+            let mut w = ReverseBitWriter::new();
+            for &(value, n_bits) in &writes {
+                w.write(value, n_bits);
+            }
+            let src = w.finish();
+
+            let mut br = ReverseBitReader::new(&src)?;
+            for (value, n_bits) in writes.into_iter().rev() {
+                let mask = u64::MAX.checked_shr(64 - n_bits as u32).unwrap_or(0);
+                prop_assert_eq!(br.read(n_bits)?, value & mask);
+            }
+            prop_assert_eq!(br.bits_remaining(), 0);
+        }
+    }
+}